@@ -0,0 +1,207 @@
+use std::fmt::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use traffic::constants::*;
+use traffic::headless::*;
+use traffic::scenario::*;
+
+// runs a base scenario once for every combination in a sweep, spread over every core, and writes
+// one row per run. anything not swept keeps the base scenario's value, e.g.
+//
+//     traffic-batch base.ron --cars 2..10:2 --mix calm --mix calm:3+aggressive:1 --seeds 1..50
+
+const USAGE: &str = "usage: traffic-batch [<scenario.ron>] [--cars <from>..<to>[:<step>]] \
+                     [--mix <temperament>[:<weight>][+...]]... [--seeds <from>..<to>] \
+                     [--seconds <seconds>] [--jobs <count>] [--out <file>]\n\
+                     ranges include both ends";
+
+struct Sweep {
+    base: Scenario,
+    cars: Vec<usize>,
+    mixes: Vec<TemperamentMix>,
+    seeds: Vec<u64>,
+    jobs: usize,
+    out: String,
+}
+
+impl Sweep {
+    // every combination, cars varying slowest
+    fn scenarios(&self) -> Vec<Scenario> {
+        let mut scenarios = vec![];
+        for &cars in &self.cars {
+            for mix in &self.mixes {
+                for &seed in &self.seeds {
+                    scenarios.push(Scenario {
+                        cars,
+                        mix: mix.clone(),
+                        seed,
+                        ..self.base.clone()
+                    });
+                }
+            }
+        }
+        scenarios
+    }
+}
+
+fn main() {
+    let sweep = parse_args();
+    let scenarios = sweep.scenarios();
+    // a sweep that overfills the road in any of its runs isn't started at all
+    for scenario in &scenarios {
+        if let Err(message) = scenario.validate() {
+            exit_with_usage(&message);
+        }
+    }
+    let jobs = sweep.jobs.clamp(1, scenarios.len().max(1));
+
+    eprintln!(
+        "{} runs of {}s simulated on {jobs} threads",
+        scenarios.len(),
+        sweep.base.seconds
+    );
+
+    // each thread takes the next run nobody has started until there are none left
+    let next = AtomicUsize::new(0);
+    let finished = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; scenarios.len()]);
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(scenario) = scenarios.get(index) else {
+                    break;
+                };
+
+                let summary = run_scenario(scenario);
+                let done = finished.fetch_add(1, Ordering::Relaxed) + 1;
+                eprintln!(
+                    "[{done}/{}] {} cars, {}, seed {}: {:.2}s",
+                    scenarios.len(),
+                    scenario.cars,
+                    scenario.mix,
+                    scenario.seed,
+                    summary.wall_time.as_secs_f32()
+                );
+                results.lock().unwrap()[index] = Some(summary);
+            });
+        }
+    });
+
+    let results = results.into_inner().unwrap();
+    let mut table = String::from(
+        "cars,mix,seed,throughput_per_minute,mean_speed,crashes,lane_changes_per_minute,\
+         longest_out_of_right_lane\n",
+    );
+    for (scenario, summary) in scenarios.iter().zip(results.into_iter().flatten()) {
+        let _ = writeln!(
+            table,
+            "{},{},{},{:.3},{:.3},{},{:.3},{:.3}",
+            scenario.cars,
+            scenario.mix,
+            scenario.seed,
+            summary.throughput,
+            summary.mean_speed,
+            summary.crashes,
+            summary.lane_changes_per_minute,
+            summary.longest_out_of_right_lane
+        );
+    }
+
+    match std::fs::write(&sweep.out, table) {
+        Ok(()) => eprintln!("saved results to {}", sweep.out),
+        Err(error) => {
+            eprintln!("couldn't save results to {}: {error}", sweep.out);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_args() -> Sweep {
+    let mut args = std::env::args().skip(1).peekable();
+
+    // the base scenario comes first, if there is one
+    let base = match args.next_if(|arg| !arg.starts_with("--")) {
+        Some(path) => Scenario::read(&path)
+            .unwrap_or_else(|error| exit_with_usage(&format!("couldn't read {path}: {error}"))),
+        None => Scenario::default(),
+    };
+
+    let mut sweep = Sweep {
+        cars: vec![base.cars],
+        mixes: vec![],
+        seeds: vec![base.seed],
+        jobs: thread::available_parallelism().map_or(1, |cores| cores.get()),
+        out: BATCH_RESULTS_PATH.to_string(),
+        base,
+    };
+
+    while let Some(arg) = args.next() {
+        let Some(value) = args.next() else {
+            exit_with_usage(&format!("{arg} needs a value"));
+        };
+
+        match arg.as_str() {
+            "--cars" => sweep.cars = parse_range(&arg, &value),
+            "--mix" => sweep.mixes.push(
+                TemperamentMix::parse(&value).unwrap_or_else(|message| exit_with_usage(&message)),
+            ),
+            "--seeds" => sweep.seeds = parse_range(&arg, &value),
+            "--seconds" => sweep.base.seconds = parse_number(&arg, &value),
+            "--jobs" => sweep.jobs = parse_number(&arg, &value),
+            "--out" => sweep.out = value,
+            _ => exit_with_usage(&format!("unknown argument {arg}")),
+        }
+    }
+
+    if sweep.mixes.is_empty() {
+        sweep.mixes.push(sweep.base.mix.clone());
+    }
+    sweep
+}
+
+// "10..200:10", "1..50" or just "30"
+fn parse_range<T>(arg: &str, value: &str) -> Vec<T>
+where
+    T: FromStr + Copy + PartialOrd + std::ops::Add<Output = T> + From<u8>,
+{
+    let (range, step) = value.split_once(':').unwrap_or((value, "1"));
+    let step: T = parse_number(arg, step);
+    if step <= T::from(0) {
+        exit_with_usage(&format!("{arg} needs a step above zero"));
+    }
+
+    let (from, to) = match range.split_once("..") {
+        Some((from, to)) => (parse_number(arg, from), parse_number(arg, to)),
+        None => {
+            let only = parse_number(arg, range);
+            (only, only)
+        }
+    };
+
+    let mut values = vec![];
+    let mut value = from;
+    while value <= to {
+        values.push(value);
+        value = value + step;
+    }
+    if values.is_empty() {
+        exit_with_usage(&format!("{arg} {range} is empty"));
+    }
+    values
+}
+
+fn parse_number<T: FromStr>(arg: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| exit_with_usage(&format!("{arg} needs a number, not {value}")))
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{message}\n{USAGE}");
+    std::process::exit(2);
+}
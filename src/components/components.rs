@@ -1,16 +1,46 @@
-use std::borrow::Borrow;
+use std::ops::RangeInclusive;
 
 use bevy::{prelude::*, sprite::*};
 use bevy_mod_picking::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::constants::*;
 use crate::events::*;
 use crate::util::*;
 
-#[derive(Clone)]
+#[derive(Clone, Reflect, Serialize, Deserialize)]
 pub struct CollisionInformation {
     pub front_distance: f32, // -1 if no collision, else distance to closest car in front
     pub last_front_distance: f32, // previous frame's value of front_distance
+    pub vehicles_ahead: Vec<VehicleAhead>, // nearest first; empty if front_distance is -1
+    pub in_contact: bool,    // touching the car in front; unlike the fields above, never misjudged
+}
+
+// a driver's current misjudgment of distance and speed, in standard deviations; drifts slowly
+// (see `perception_error_system`) so estimates wander rather than jitter from tick to tick
+#[derive(Clone, Default, Reflect, Serialize, Deserialize)]
+pub struct EstimationError {
+    pub distance: f32,
+    pub speed: f32,
+}
+
+// a vehicle in this car's lane within sight distance and not hidden behind a wider vehicle
+#[derive(Clone, Reflect, Serialize, Deserialize)]
+pub struct VehicleAhead {
+    pub entity: Entity,
+    pub distance: f32,
+    pub speed: f32,
+}
+
+// another car picked up by one of this car's sensors
+#[derive(Clone, Reflect, Serialize, Deserialize)]
+pub struct Detection {
+    pub entity: Entity,
+    pub sensor: Sensor,
+    pub lanes: RangeInclusive<i32>, // lanes the detected car is over
+    pub gap: f32,                   // bumper-to-bumper distance; negative when alongside
+    pub relative_speed: f32,        // positive when the detected car is pulling away
+    pub in_blind_spot: bool,
 }
 
 // COMPONENTS
@@ -19,20 +49,25 @@ pub struct CollisionInformation {
 #[derive(Component)]
 pub struct MainCamera;
 
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Reflect)]
+#[reflect(Component)]
 pub struct Car;
 
 // an entity that has a position in a certain lane
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct LaneEntity(pub i32);
 
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Reflect)]
+#[reflect(Component)]
 pub struct Collider;
 
-#[derive(Component, Clone, Deref, DerefMut)]
+#[derive(Component, Clone, Deref, DerefMut, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct Velocity(pub Vec2);
 
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Reflect)]
+#[reflect(Component)]
 pub struct Friction;
 
 #[derive(Component)]
@@ -45,42 +80,152 @@ pub struct MouseText;
 pub struct Lane(pub Vec2);
 
 // add to an entity to indicate it has been selected
-#[derive(Component)]
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct SelectedEntity;
 
-#[derive(Component, Clone)]
+#[derive(Component, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct DriverAgent {
     pub driver_state: DriverState,
     pub collision_information: CollisionInformation,
     pub lawfulness: DriverLawfulness,
     pub temperament: DriverTemperament,
     pub patience: DriverPatience,
+    pub attentiveness: DriverAttentiveness,
+    pub judgment: DriverJudgment,
+    pub estimation_error: EstimationError,
 }
 
 impl DriverAgent {
     pub fn with_lawfulness(mut self, lawfulness: DriverLawfulness) -> Self {
         self.lawfulness = lawfulness;
-        return self;
+        self
     }
     pub fn with_temperament(mut self, temperament: DriverTemperament) -> Self {
         self.temperament = temperament;
-        return self;
+        self
     }
     pub fn with_patience(mut self, patience: DriverPatience) -> Self {
         self.patience = patience;
-        return self;
+        self
+    }
+    pub fn with_attentiveness(mut self, attentiveness: DriverAttentiveness) -> Self {
+        self.attentiveness = attentiveness;
+        self
     }
+    pub fn with_judgment(mut self, judgment: DriverJudgment) -> Self {
+        self.judgment = judgment;
+        self
+    }
+    pub fn with_profile(self, profile: DriverProfile) -> Self {
+        match profile {
+            DriverProfile::Commuter => self
+                .with_lawfulness(DriverLawfulness::Orderly)
+                .with_temperament(DriverTemperament::Calm)
+                .with_patience(DriverPatience::Normal)
+                .with_attentiveness(DriverAttentiveness::Normal)
+                .with_judgment(DriverJudgment::Accurate),
+            DriverProfile::SundayDriver => self
+                .with_lawfulness(DriverLawfulness::Orderly)
+                .with_temperament(DriverTemperament::Passive)
+                .with_patience(DriverPatience::Enlightened)
+                .with_attentiveness(DriverAttentiveness::Attentive)
+                .with_judgment(DriverJudgment::Cautious),
+            DriverProfile::Speeder => self
+                .with_lawfulness(DriverLawfulness::Chaotic)
+                .with_temperament(DriverTemperament::Aggressive)
+                .with_patience(DriverPatience::Wild)
+                .with_attentiveness(DriverAttentiveness::Normal)
+                .with_judgment(DriverJudgment::Overconfident),
+            DriverProfile::RoadRager => self
+                .with_lawfulness(DriverLawfulness::Chaotic)
+                .with_temperament(DriverTemperament::Psychotic)
+                .with_patience(DriverPatience::Wild)
+                .with_attentiveness(DriverAttentiveness::Distracted)
+                .with_judgment(DriverJudgment::Overconfident),
+            DriverProfile::Distracted => self
+                .with_lawfulness(DriverLawfulness::Orderly)
+                .with_temperament(DriverTemperament::Calm)
+                .with_patience(DriverPatience::Patient)
+                .with_attentiveness(DriverAttentiveness::Distracted)
+                .with_judgment(DriverJudgment::Sloppy),
+        }
+    }
+}
+
+// forward speed gained per second over the last tick, negative when slowing; see
+// `measure_acceleration_system`
+#[derive(Component, Clone, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct Acceleration {
+    pub value: f32,
+    pub last_speed: f32,
+}
+
+// what kind of vehicle this is; every spawned vehicle is currently a `Car`, the others only
+// differ in how they're colored
+#[derive(
+    Component, Clone, Copy, Default, PartialEq, Eq, Hash, Debug, Reflect, Serialize, Deserialize,
+)]
+#[reflect(Component)]
+pub enum VehicleClass {
+    #[default]
+    Car,
+    Truck,
+    Motorcycle,
 }
 
+// one cell of the heatmap overlay, a stretch of `HEATMAP_CELL_LENGTH` of one lane
 #[derive(Component)]
+pub struct HeatmapCell {
+    pub lane: i32,
+    pub segment: usize,
+}
+
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct LaneChanger;
 
-#[derive(Component)]
+// a frozen car is held in place: it doesn't drive or change lanes, but others still see it
+#[derive(Component, Reflect)]
+#[reflect(Component)]
+pub struct Frozen;
+
+#[derive(Component, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
 pub struct ActiveLaneChange {
     pub lane_change_direction: LaneChangeDirection,
     pub lane_target: i32,
 }
 
+// blinking indicator shown while a car waits to move into `lane_target`; it becomes an
+// `ActiveLaneChange` once the signal has been on long enough and the lane is open
+#[derive(Component, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct TurnSignal {
+    pub direction: LaneChangeDirection,
+    pub lane_target: i32,
+    pub elapsed: f32,             // seconds the signal has been on
+    pub checked_blind_spot: bool, // rolled once per signal from the driver's attentiveness
+}
+
+// added to the car directly behind a signaling car in its target lane
+#[derive(Component, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct CourtesyResponse {
+    pub merging_entity: Entity,
+    pub response: MergeResponse,
+    pub gap: f32, // distance from this car's front to the merging car's rear
+}
+
+// everything a car's sensors picked up this tick, refreshed by `perception_system`
+#[derive(Component, Clone, Default, Reflect, Serialize, Deserialize)]
+#[reflect(Component)]
+pub struct Perception {
+    pub detections: Vec<Detection>,
+}
+
 // BUNDLES
 #[derive(Bundle, Clone)]
 pub struct CarBundle {
@@ -89,38 +234,52 @@ pub struct CarBundle {
     pub lane: LaneEntity,
     pub collider: Collider,
     pub velocity: Velocity,
+    pub acceleration: Acceleration,
     pub friction: Friction,
     pub driver_agent: DriverAgent,
+    pub perception: Perception,
+    pub vehicle_class: VehicleClass,
 }
 
 impl CarBundle {
     pub fn new(position: Vec3, mesh: Mesh2dHandle, material: Handle<ColorMaterial>) -> CarBundle {
         CarBundle {
             material_bundle: MaterialMesh2dBundle {
-                mesh: mesh,
+                mesh,
                 transform: Transform {
                     translation: position,
                     scale: CAR_SIZE,
                     ..default()
                 },
-                material: material,
+                material,
                 ..default()
             },
             car: Car,
             lane: LaneEntity(lane_idx_from_screen_pos(&position.truncate())),
             collider: Collider,
             velocity: Velocity(CAR_INITIAL_DIRECTION),
+            acceleration: Acceleration {
+                value: 0.,
+                last_speed: CAR_INITIAL_DIRECTION.y,
+            },
             friction: Friction,
             driver_agent: DriverAgent {
-                driver_state: DriverState::Normal,
+                driver_state: DriverState::Cruising,
                 collision_information: CollisionInformation {
                     front_distance: -1.,
                     last_front_distance: -1.,
+                    vehicles_ahead: vec![],
+                    in_contact: false,
                 },
                 lawfulness: DriverLawfulness::Orderly,
                 temperament: DriverTemperament::Calm,
                 patience: DriverPatience::Normal,
+                attentiveness: DriverAttentiveness::Normal,
+                judgment: DriverJudgment::Accurate,
+                estimation_error: EstimationError::default(),
             },
+            perception: Perception::default(),
+            vehicle_class: VehicleClass::Car,
         }
     }
 
@@ -131,33 +290,45 @@ impl CarBundle {
         lawfulness: DriverLawfulness,
         temperament: DriverTemperament,
         patience: DriverPatience,
+        attentiveness: DriverAttentiveness,
     ) -> CarBundle {
         CarBundle {
             material_bundle: MaterialMesh2dBundle {
-                mesh: mesh,
+                mesh,
                 transform: Transform {
                     translation: position,
                     scale: CAR_SIZE,
                     ..default()
                 },
-                material: material,
+                material,
                 ..default()
             },
             car: Car,
             lane: LaneEntity(lane_idx_from_screen_pos(&position.truncate())),
             collider: Collider,
             velocity: Velocity(CAR_INITIAL_DIRECTION * SPEED_LIMIT),
+            acceleration: Acceleration {
+                value: 0.,
+                last_speed: CAR_INITIAL_DIRECTION.y * SPEED_LIMIT,
+            },
             friction: Friction,
             driver_agent: DriverAgent {
-                driver_state: DriverState::Normal,
+                driver_state: DriverState::Cruising,
                 collision_information: CollisionInformation {
                     front_distance: -1.,
                     last_front_distance: -1.,
+                    vehicles_ahead: vec![],
+                    in_contact: false,
                 },
                 lawfulness,
                 temperament,
                 patience,
+                attentiveness,
+                judgment: DriverJudgment::Accurate,
+                estimation_error: EstimationError::default(),
             },
+            perception: Perception::default(),
+            vehicle_class: VehicleClass::Car,
         }
     }
 }
@@ -201,15 +372,35 @@ pub fn spawn_car_at_lane(
     lawfulness: DriverLawfulness,
     temperament: DriverTemperament,
     patience: DriverPatience,
-) {
+    attentiveness: DriverAttentiveness,
+) -> Entity {
     let car_x = lane_idx_to_center(lane_idx).x;
     let car_y = BOTTOM_WALL + WALL_THICKNESS;
     let car_pos = Vec3::new(car_x, car_y, 0.);
 
-    commands.spawn((
-        CarBundle::new_with_behavior(car_pos, mesh, material, lawfulness, temperament, patience),
+    spawn_car(
+        commands,
+        CarBundle::new_with_behavior(
+            car_pos,
+            mesh,
+            material,
+            lawfulness,
+            temperament,
+            patience,
+            attentiveness,
+        ),
+    )
+}
+
+pub fn spawn_car(commands: &mut Commands, car_bundle: CarBundle) -> Entity {
+    commands.spawn(pickable_car(car_bundle)).id()
+}
+
+pub fn pickable_car(car_bundle: CarBundle) -> impl Bundle {
+    (
+        car_bundle,
         PickableBundle::default(),
         On::<Pointer<Select>>::send_event::<SelectEntityEvent>(),
         On::<Pointer<Deselect>>::send_event::<DeselectEntityEvent>(),
-    ));
+    )
 }
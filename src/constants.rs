@@ -13,12 +13,126 @@ pub const CAR_INITIAL_DIRECTION: Vec2 = Vec2::new(0., 0.5);
 pub const CAR_GAS_POWER: f32 = 10.; // how much velocity the car gains per frame
 pub const CAR_BRAKE_POWER: f32 = 15.;
 pub const CAR_SIGHT_DISTANCE: f32 = 300.;
+pub const CAR_REAR_SIGHT_DISTANCE: f32 = 200.;
+pub const CAR_BLIND_SPOT_LENGTH: f32 = 40.; // how far behind a car's rear bumper its blind spots reach
+pub const CAR_STOPPED_SPEED: f32 = 1.; // below this forward speed a car counts as stopped
+
+// PERCEPTION ERROR
+pub const PERCEPTION_DISTANCE_NOISE_PCT: f32 = 0.05; // std dev as a fraction of the distance being judged
+pub const PERCEPTION_SPEED_NOISE: f32 = 10.; // std dev of relative speed at full sight distance
+pub const PERCEPTION_ERROR_CORRELATION: f32 = 0.98; // how much of last tick's error carries over
+
+// ANTICIPATION
+pub const ANTICIPATION_DEPTH: usize = 3; // how many vehicles ahead a driver keeps track of
+pub const ANTICIPATION_HORIZON: f32 = 4.; // seconds; slowdowns further out than this are ignored
+pub const ANTICIPATION_BRAKE_PCT: f32 = 0.5; // fraction of brake power used for the nearest slowdown
+
+// TURN SIGNALS
+pub const TURN_SIGNAL_MIN_DURATION: f32 = 1.; // seconds a car signals before it starts moving over
+pub const TURN_SIGNAL_TIMEOUT: f32 = 5.; // seconds a car waits for a gap before giving up
+pub const TURN_SIGNAL_BLINK_PERIOD: f32 = 0.35;
+pub const COURTESY_BRAKE_PCT: f32 = 0.3; // fraction of brake power used when opening a gap for a merging car
+
+// CAMERA
+pub const CAMERA_ZOOM_STEP: f32 = 0.1; // fraction the view shrinks per mouse wheel notch
+pub const CAMERA_MIN_SCALE: f32 = 0.1; // world units per pixel, fully zoomed in
+pub const CAMERA_MAX_SCALE: f32 = 10.; // world units per pixel, fully zoomed out
+pub const CAMERA_FIT_MARGIN: f32 = 20.; // world units left around the road when fitting it in view
+
+// VEHICLE COLORS
+pub const COLOR_STEADY_ACCELERATION: f32 = 100.; // speed change per second still shown as steady; friction alone is below this
+pub const COLOR_HARD_BRAKING: f32 = 500.; // speed lost per second from which braking shows as hard
+
+// PLOTS
+pub const METRICS_SAMPLE_PERIOD: f32 = 0.25; // simulated seconds between samples
+pub const METRICS_HISTORY: f32 = 600.; // simulated seconds of samples kept; the longest plot window
+pub const PLOT_WINDOW: f32 = 60.; // default simulated seconds shown in the plots
+pub const PLOT_HEIGHT: f32 = 70.;
+pub const HISTOGRAM_BINS: usize = 12;
+pub const HISTOGRAM_MAX_SPEED: f32 = SPEED_LIMIT * 1.6;
+
+// SPACE-TIME DIAGRAM
+pub const TRAJECTORY_SAMPLE_PERIOD: f32 = 0.1; // simulated seconds between trajectory points
+pub const TRAJECTORY_HISTORY: f32 = 180.; // simulated seconds of trajectory kept; the longest window
+pub const TRAJECTORY_WINDOW: f32 = 30.; // default simulated seconds shown
+pub const TRAJECTORY_PICK_DISTANCE: f32 = 6.; // pixels from a trajectory that still count as clicking it
+
+// BREAKPOINTS
+pub const BREAKPOINT_HITS_LENGTH: usize = 100; // most recent hits kept in the list
+pub const BREAKPOINT_DEFAULT_GAP: f32 = 10.;
+pub const BREAKPOINT_DEFAULT_DECELERATION: f32 = COLOR_HARD_BRAKING;
+
+// DECISION TRACE
+pub const DECISION_TRACE_LENGTH: f32 = 10.; // simulated seconds of decisions kept per vehicle
+pub const DECISION_TRACE_SHOWN: usize = 200; // most recent matching decisions listed in the inspector
+pub const DECISION_TRACE_PATH: &str = "decision_trace.csv";
+
+// PROFILER
+pub const PROFILE_RUNS: usize = 600; // most recent runs of each system summarized
+pub const PROFILE_REPORT_PATH: &str = "profile.txt";
+pub const HEADLESS_CARS: usize = 20; // cars spawned for a headless run unless `--cars` says otherwise
+
+// BATCH RUNS
+pub const SCENARIO_SECONDS: f32 = 60.; // simulated seconds a scenario runs for unless it says otherwise
+pub const BATCH_RESULTS_PATH: &str = "batch_results.csv";
+
+// SIMULATION SPEED
+pub const SIMULATION_SPEEDS: [f32; 6] = [0.1, 0.5, 1., 2., 5., 10.]; // one step past the last runs as fast as possible
+pub const DEFAULT_SPEED_STEP: usize = 2; // 1x
+pub const SPEED_MAX_DELTA: f32 = 0.25; // most virtual seconds a frame may advance at a fixed speed; bevy's default
+pub const FAST_FORWARD_FRAME_BUDGET: f32 = 1. / 30.; // real seconds per frame aimed for when running as fast as possible
+pub const FAST_FORWARD_MAX_TICKS: u32 = 1000; // most ticks run in one frame when running as fast as possible
+pub const SPEED_RATE_SMOOTHING: f32 = 0.05; // weight of each frame's reading in the measured speed
+
+// INVARIANT CHECKS
+pub const INVARIANT_VIOLATIONS_LENGTH: usize = 200; // most recent violations kept in the list
+pub const INVARIANT_MAX_SPEED: f32 = SPEED_LIMIT * 2.; // well past the fastest temperament's top speed
+pub const INVARIANT_LANE_CENTER_TOLERANCE: f32 = 1.; // how far a car not changing lanes may sit off its lane's center
+
+// SAVE / LOAD
+pub const SAVE_PATH: &str = "traffic.save";
+
+// REWIND
+pub const REWIND_LENGTH: f32 = 30.; // simulated seconds of snapshots kept to step back through
+
+// REPLAY
+pub const RECORDING_PATH: &str = "traffic.replay";
+pub const REPLAY_MIN_SPEED: f32 = 0.1;
+pub const REPLAY_MAX_SPEED: f32 = 10.;
+pub const REPLAY_RESOLUTION: f32 = 1. / 16.; // units recorded positions and speeds are rounded to
+pub const REPLAY_MAX_LENGTH: f32 = 600.; // simulated seconds a recording runs before it stops and saves itself
+
+// VEHICLE OVERLAYS
+pub const TRAIL_SAMPLE_PERIOD: f32 = 0.1; // simulated seconds between trail points
+pub const TRAIL_MAX_LENGTH: f32 = 20.; // simulated seconds of trail kept per vehicle
+pub const TRAIL_LENGTH: f32 = 5.; // default simulated seconds of trail drawn
+pub const TRAIL_COLOR: Color = Color::rgb(0.4, 0.8, 1.);
+pub const PREDICTED_PATH_COLOR: Color = Color::rgb(1., 0.8, 0.2);
+pub const STOPPING_ENVELOPE_COLOR: Color = Color::rgba(0.5, 1., 0.5, 0.6);
+pub const STOPPING_ENVELOPE_OVERLAP_COLOR: Color = Color::rgba(1., 0.2, 0.2, 0.8);
+
+// HEATMAP
+pub const HEATMAP_CELL_LENGTH: f32 = 50.;
+pub const HEATMAP_SMOOTHING_WINDOW: f32 = 10.; // default simulated seconds for the smoothing to mostly forget
+pub const HEATMAP_MAX_DENSITY: f32 = 25.; // cars per 1000 units of lane shaded at full strength
+pub const HEATMAP_MAX_FLOW: f32 = 300.; // cars per minute shaded at full strength
+pub const HEATMAP_ALPHA: f32 = 0.6;
+
+// MINIMAP
+pub const MINIMAP_WIDTH: f32 = 150.; // pixels; the height follows the road's proportions
+pub const MINIMAP_DOT_RADIUS: f32 = 2.;
+pub const JAM_SEGMENT_LENGTH: f32 = 200.; // lane length over which local density is measured
+pub const JAM_DENSITY: f32 = 0.0125; // cars per unit of lane length; above this a segment is jammed
+
+// EDITING
+pub const HISTORY_LENGTH: usize = 200; // undoable edits kept; the oldest are dropped first
 
 // how far to either side of the car will be checked when attempting to change lanes
 pub const CAR_SIDE_CHECK_DISTANCE: f32 = LANE_WIDTH + (CAR_SIZE.y / 2.);
 
 pub const LANE_WIDTH: f32 = 40.;
 pub const LANE_WIDTH_DOUBLE: f32 = LANE_WIDTH * 2.;
+pub const LANE_CHANGE_DURATION: f32 = LANE_WIDTH / CAR_GAS_POWER; // seconds to move one lane over
 pub const LANE_STRIP_SIZE: Vec3 = Vec3::new(5., 10., 0.);
 pub const NUM_LANES: i32 = 2;
 
@@ -29,6 +143,7 @@ pub const SCORE_COLOR: Color = Color::rgb(1., 0.5, 0.5);
 pub const STRIPE_COLOR: Color = Color::WHITE;
 pub const TEXT_COLOR: Color = Color::rgb(0.5, 0.5, 1.);
 pub const WALL_COLOR: Color = Color::WHITE;
+pub const TURN_SIGNAL_COLOR: Color = Color::ORANGE;
 
 pub const SCOREBOARD_FONT_SIZE: f32 = 40.;
 pub const SCOREBOARD_TEXT_PADDING: Val = Val::Px(5.);
@@ -42,6 +157,7 @@ pub const BOTTOM_WALL: f32 = -600.;
 pub const TOP_WALL: f32 = 600.;
 
 // ENVIRONMENT
+pub const SIMULATION_SEED: u64 = 1;
 pub const FRICTION_DECAY: f32 = 0.996;
 pub const SPEED_LIMIT: f32 = 200.;
 
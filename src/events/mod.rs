@@ -2,6 +2,7 @@ use bevy::prelude::*;
 use bevy_mod_picking::prelude::*;
 
 use crate::components::*;
+use crate::util::*;
 
 #[derive(Event, Default)]
 pub struct CollisionEvent;
@@ -33,7 +34,104 @@ impl From<ListenerInput<Pointer<Deselect>>> for DeselectEntityEvent {
     }
 }
 
-// requests to modify an entity with `SelectedEntity` component
-// by replacing its `DriverAgent` component with the provided
+#[derive(Debug, Clone)]
+pub enum DriverAgentEdit {
+    Lawfulness(DriverLawfulness),
+    Temperament(DriverTemperament),
+    Patience(DriverPatience),
+    Profile(DriverProfile),
+}
+
+// requests to modify the `DriverAgent` of every entity with `SelectedEntity` component;
+// only the edited behavior changes, each car keeps its own state
+#[derive(Event)]
+pub struct ModifySelectedDriverAgentEvent(pub DriverAgentEdit);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectionAction {
+    Delete,
+    Duplicate,
+    Freeze,
+    Unfreeze,
+}
+
+// requests that an action be applied to every car with `SelectedEntity` component
+#[derive(Event)]
+pub struct SelectionActionEvent(pub SelectionAction);
+
+// requests to replace one reflected component on `entity` with the provided value;
+// sent by the inspector, the concrete type is looked up in the type registry
+#[derive(Event)]
+pub struct ModifyComponentEvent {
+    pub entity: Entity,
+    pub component: Box<dyn Reflect>,
+}
+
+// an agent moved from one `DriverState` to another during the last tick
+#[derive(Event)]
+pub struct DriverStateChangeEvent {
+    pub entity: Entity,
+    pub from: DriverState,
+    pub to: DriverState,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeInteraction {
+    Signaled,
+    Responded(MergeResponse),
+    Merged,
+    Abandoned,
+}
+
+// one step of a merge: a car signaling, a follower reacting to it, and the merge either
+// happening or being given up; tallied into `MergeCooperationStats`
+#[derive(Event)]
+pub struct MergeInteractionEvent {
+    pub merging_entity: Entity,
+    pub responder: Option<Entity>,
+    pub interaction: MergeInteraction,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HistoryRequest {
+    Undo,
+    Redo,
+    JumpTo(usize), // undo or redo until this many edits are applied
+}
+
+// requests to move through the edit `History`
+#[derive(Event)]
+pub struct HistoryRequestEvent(pub HistoryRequest);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayRequest {
+    StartRecording,
+    StopRecording, // and write the recording to `RECORDING_PATH`
+    Open,          // play back the recording at `RECORDING_PATH`
+    Close,         // back to the live simulation, as it was when the replay was opened
+}
+
+// requests to record the simulation or play a recording back; see `replay`
+#[derive(Event)]
+pub struct ReplayRequestEvent(pub ReplayRequest);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RewindRequest {
+    StepBack,
+    StepForward,
+    JumpTo(usize), // index into `Rewind::snapshots`
+}
+
+// requests to move the paused simulation through its recent past; see `rewind`
+#[derive(Event)]
+pub struct RewindRequestEvent(pub RewindRequest);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SaveRequest {
+    Save,
+    Load,
+}
+
+// requests to save or load the whole simulation at `SaveSlot::path`; see `save`
 #[derive(Event)]
-pub struct ModifySelectedDriverAgentEvent(pub DriverAgent);
+pub struct SaveRequestEvent(pub SaveRequest);
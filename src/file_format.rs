@@ -0,0 +1,54 @@
+use bincode::Options;
+use serde::{de::DeserializeOwned, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// the layout shared by every file the simulation writes: a four byte magic naming the kind of
+// file, a little-endian format version, then the contents in bincode with variable-length
+// integers. files of another kind or version are refused rather than misread
+
+pub fn write_versioned<T: Serialize>(
+    path: impl AsRef<Path>,
+    magic: [u8; 4],
+    version: u32,
+    value: &T,
+) -> io::Result<()> {
+    let body = bincode::DefaultOptions::new()
+        .serialize(value)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+
+    let mut bytes = Vec::with_capacity(body.len() + 8);
+    bytes.extend_from_slice(&magic);
+    bytes.extend_from_slice(&version.to_le_bytes());
+    bytes.extend_from_slice(&body);
+
+    fs::write(path, bytes)
+}
+
+pub fn read_versioned<T: DeserializeOwned>(
+    path: impl AsRef<Path>,
+    magic: [u8; 4],
+    version: u32,
+) -> io::Result<T> {
+    let bytes = fs::read(path)?;
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    if bytes.len() < 8 || bytes[..4] != magic {
+        return Err(invalid(format!(
+            "not a {} file",
+            String::from_utf8_lossy(&magic)
+        )));
+    }
+
+    let file_version = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+    if file_version != version {
+        return Err(invalid(format!(
+            "format version {file_version}, expected {version}"
+        )));
+    }
+
+    bincode::DefaultOptions::new()
+        .deserialize(&bytes[8..])
+        .map_err(|error| invalid(error.to_string()))
+}
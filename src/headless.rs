@@ -0,0 +1,272 @@
+use bevy::{
+    ecs::{event::ManualEventReader, schedule::ExecutorKind},
+    prelude::*,
+    sprite::Mesh2dHandle,
+    time::TimeUpdateStrategy,
+    utils::HashMap,
+};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::time::{Duration, Instant};
+
+use crate::components::*;
+use crate::constants::*;
+use crate::events::*;
+use crate::profiling::*;
+use crate::resources::*;
+use crate::scenario::*;
+use crate::simulation::*;
+use crate::util::*;
+
+// running the simulation without a window, as fast as it will go. every update advances exactly
+// one fixed tick, so a run with the same scenario comes out the same every time
+
+pub fn run_headless(
+    seconds: f32,
+    cars: usize,
+    save_slot: SaveSlot,
+    check_invariants: bool,
+    profile: bool,
+) -> Result<(), String> {
+    let scenario = Scenario {
+        seconds,
+        cars,
+        ..default()
+    };
+    scenario.validate()?;
+    let mut app = build_app(&scenario, save_slot, check_invariants);
+    app.world.resource::<Profiler>().set_enabled(profile);
+    let timestep = app.world.resource::<Time<Fixed>>().timestep();
+
+    let start = Instant::now();
+    while app.world.resource::<SimulationClock>().elapsed < seconds {
+        app.update();
+    }
+    let wall_time = start.elapsed();
+
+    let clock = app.world.resource::<SimulationClock>().clone();
+    println!(
+        "simulated {:.2}s ({} ticks) in {:.2}s",
+        clock.elapsed,
+        clock.ticks,
+        wall_time.as_secs_f32()
+    );
+
+    // how long each system took
+    if profile {
+        let counts = EntityCounts::count(&mut app.world);
+        let report = app.world.resource::<Profiler>().report(&counts, timestep);
+        println!("{report}");
+    }
+
+    // each violation was logged as it happened; this is the tally
+    let checks = app.world.resource::<InvariantChecks>();
+    if checks.enabled {
+        println!("{} invariant violations", checks.total);
+        for violation in &checks.violations {
+            println!(
+                "tick {:>6} {:?} {:?}: {}",
+                violation.tick, violation.invariant, violation.entities, violation.detail
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// a windowless app with the scenario's cars on the road, ready for its first update
+pub fn build_app(scenario: &Scenario, save_slot: SaveSlot, check_invariants: bool) -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        // loading a save respawns its cars with meshes
+        AssetPlugin::default(),
+        SimulationPlugin { headless: true },
+    ))
+    .init_asset::<Mesh>()
+    .insert_resource(save_slot)
+    .insert_resource(SimulationRng(ChaCha8Rng::seed_from_u64(scenario.seed)))
+    .insert_resource(CarFollowingConfig {
+        anticipation: scenario.anticipation,
+        ..default()
+    })
+    .insert_resource(PerceptionNoiseConfig {
+        enabled: scenario.perception_noise,
+        ..default()
+    })
+    .insert_resource(InvariantChecks {
+        enabled: check_invariants,
+        ..default()
+    });
+
+    let scenario = scenario.clone();
+    app.add_systems(Startup, move |mut commands: Commands| {
+        spawn_cars(&mut commands, &scenario)
+    });
+
+    let timestep = app.world.resource::<Time<Fixed>>().timestep();
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+
+    app.finish();
+    app.cleanup();
+    app
+}
+
+// what a batch run reports about one scenario, measured after its warmup
+#[derive(Clone, Debug, Default)]
+pub struct RunSummary {
+    pub throughput: f32, // cars per minute past the end of the road, across every lane
+    pub mean_speed: f32, // over every car at every tick
+    pub crashes: usize,  // cars that ran into the car ahead; the same car can crash again
+    pub lane_changes_per_minute: f32,
+    pub longest_out_of_right_lane: f32, // by any orderly car, which keeps right unless passing
+    pub wall_time: Duration,
+}
+
+// runs the scenario to the end on the calling thread, leaving the other cores to other runs
+pub fn run_scenario(scenario: &Scenario) -> RunSummary {
+    let mut app = build_app(scenario, SaveSlot::default(), false);
+    let single_threaded = |schedule: &mut Schedule| {
+        schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+    };
+    app.edit_schedule(FixedUpdate, single_threaded)
+        .edit_schedule(Update, single_threaded);
+
+    let start = Instant::now();
+    let mut measurements = RunMeasurements::default();
+    loop {
+        let elapsed = app.world.resource::<SimulationClock>().elapsed;
+        if elapsed >= scenario.seconds {
+            break;
+        }
+        app.update();
+        measurements.observe(&mut app.world, elapsed >= scenario.warmup);
+    }
+
+    measurements.summary(start.elapsed())
+}
+
+#[derive(Default)]
+struct RunMeasurements {
+    state_changes: ManualEventReader<DriverStateChangeEvent>,
+    positions: HashMap<Entity, f32>, // each car's position along the road at the last tick
+    out_of_right_lane: HashMap<Entity, f32>, // orderly cars out of the right lane, and since when
+    longest_out_of_right_lane: f32,
+    lane_changes_at_start: Option<usize>,
+    lane_changes: usize,
+    measured_time: f32,
+    speed_sum: f32,
+    speed_samples: usize,
+    passed: usize,
+    crashes: usize,
+}
+
+impl RunMeasurements {
+    // after every tick; `measuring` is false until the warmup is over
+    fn observe(&mut self, world: &mut World, measuring: bool) {
+        let crashes = self
+            .state_changes
+            .read(world.resource::<Events<DriverStateChangeEvent>>())
+            .filter(|change| change.to == DriverState::Crashed)
+            .count();
+
+        let total_lane_changes = world.resource::<TrafficMetrics>().total_lane_changes;
+        let timestep = world.resource::<Time<Fixed>>().timestep().as_secs_f32();
+        let now = world.resource::<SimulationClock>().elapsed;
+
+        let mut query = world.query_filtered::<
+            (Entity, &Transform, &Velocity, &LaneEntity, &DriverAgent),
+            With<Car>,
+        >();
+        let mut passed = 0;
+        let mut speed_sum = 0.;
+        let mut speed_samples = 0;
+        for (entity, transform, velocity, lane, agent) in query.iter(world) {
+            let position = transform.translation.y;
+            // wrapping back to the bottom is the only way a car's position goes down
+            if self
+                .positions
+                .insert(entity, position)
+                .is_some_and(|last| position < last)
+            {
+                passed += 1;
+            }
+            speed_sum += velocity.y;
+            speed_samples += 1;
+
+            if measuring && agent.lawfulness == DriverLawfulness::Orderly {
+                if lane.0 == NUM_LANES - 1 {
+                    self.out_of_right_lane.remove(&entity);
+                } else {
+                    let left_at = *self.out_of_right_lane.entry(entity).or_insert(now);
+                    self.longest_out_of_right_lane =
+                        self.longest_out_of_right_lane.max(now - left_at);
+                }
+            }
+        }
+
+        if !measuring {
+            return;
+        }
+        let lane_changes_at_start = *self.lane_changes_at_start.get_or_insert(total_lane_changes);
+        self.lane_changes = total_lane_changes - lane_changes_at_start;
+        self.measured_time += timestep;
+        self.speed_sum += speed_sum;
+        self.speed_samples += speed_samples;
+        self.passed += passed;
+        self.crashes += crashes;
+    }
+
+    fn summary(&self, wall_time: Duration) -> RunSummary {
+        let minutes = self.measured_time / 60.;
+        let per_minute = |count: usize| {
+            if minutes > 0. {
+                count as f32 / minutes
+            } else {
+                0.
+            }
+        };
+
+        RunSummary {
+            throughput: per_minute(self.passed),
+            mean_speed: if self.speed_samples > 0 {
+                self.speed_sum / self.speed_samples as f32
+            } else {
+                0.
+            },
+            crashes: self.crashes,
+            lane_changes_per_minute: per_minute(self.lane_changes),
+            longest_out_of_right_lane: self.longest_out_of_right_lane,
+            wall_time,
+        }
+    }
+}
+
+// evenly spaced along every lane, each driven by the next of the scenario's temperaments
+fn spawn_cars(commands: &mut Commands, scenario: &Scenario) {
+    let cars = scenario.cars;
+    let per_lane = cars.div_ceil(NUM_LANES as usize).max(1);
+    let spacing = (TOP_WALL - BOTTOM_WALL) / per_lane as f32;
+
+    for (i, temperament) in scenario.temperaments().into_iter().enumerate() {
+        let lane = (i % NUM_LANES as usize) as i32;
+        let position = Vec3::new(
+            lane_idx_to_center(lane).x,
+            BOTTOM_WALL + WALL_THICKNESS + (i / NUM_LANES as usize) as f32 * spacing,
+            0.,
+        );
+
+        spawn_car(
+            commands,
+            CarBundle::new_with_behavior(
+                position,
+                Mesh2dHandle::default(),
+                Handle::default(),
+                scenario.lawfulness.clone(),
+                temperament,
+                scenario.patience.clone(),
+                scenario.attentiveness.clone(),
+            ),
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use bevy::{prelude::*, sprite::Mesh2dHandle, utils::HashMap};
+
+use crate::components::*;
+use crate::constants::*;
+use crate::snapshot::*;
+
+// undo / redo for everything the user changes by hand. each edit stores enough to be replayed in
+// either direction: reflected values for component and resource edits, and full snapshots for
+// cars that are added or removed. the simulation itself is never recorded
+
+// a car as it was just before being removed, enough to put it back where it was; in-progress
+// maneuvers (turn signals, lane changes) aren't kept
+#[derive(Clone)]
+pub struct CarSnapshot {
+    pub transform: Transform,
+    pub velocity: Velocity,
+    pub lane: LaneEntity,
+    pub driver_agent: DriverAgent,
+    pub mesh: Mesh2dHandle,
+    pub material: Handle<ColorMaterial>,
+    pub vehicle_class: VehicleClass,
+    pub frozen: bool,
+}
+
+impl CarSnapshot {
+    pub fn capture(world: &World, entity: Entity) -> Option<CarSnapshot> {
+        let entity = world.get_entity(entity)?;
+
+        Some(CarSnapshot {
+            transform: *entity.get::<Transform>()?,
+            velocity: entity.get::<Velocity>()?.clone(),
+            lane: entity.get::<LaneEntity>()?.clone(),
+            driver_agent: entity.get::<DriverAgent>()?.clone(),
+            mesh: entity.get::<Mesh2dHandle>()?.clone(),
+            material: entity.get::<Handle<ColorMaterial>>()?.clone(),
+            vehicle_class: *entity.get::<VehicleClass>()?,
+            frozen: entity.contains::<Frozen>(),
+        })
+    }
+
+    fn to_bundle(&self) -> CarBundle {
+        let mut car_bundle = CarBundle::new(
+            self.transform.translation,
+            self.mesh.clone(),
+            self.material.clone(),
+        );
+        car_bundle.material_bundle.transform = self.transform;
+        car_bundle.velocity = self.velocity.clone();
+        car_bundle.acceleration.last_speed = self.velocity.y;
+        car_bundle.lane = self.lane.clone();
+        car_bundle.driver_agent = self.driver_agent.clone();
+        car_bundle.vehicle_class = self.vehicle_class;
+
+        car_bundle
+    }
+}
+
+// a car added or removed by an edit; the snapshot is taken whenever the car is removed, so
+// it's empty for a freshly spawned car until its spawn is first undone
+pub struct CarRecord {
+    pub entity: Entity,
+    pub snapshot: Option<CarSnapshot>,
+}
+
+impl CarRecord {
+    pub fn spawned(entity: Entity) -> CarRecord {
+        CarRecord {
+            entity,
+            snapshot: None,
+        }
+    }
+}
+
+// one reflected component before and after an edit; `None` means the entity didn't have it
+pub struct ComponentChange {
+    pub entity: Entity,
+    pub before: Option<Box<dyn Reflect>>,
+    pub after: Option<Box<dyn Reflect>>,
+}
+
+// one reflected resource before and after an edit
+pub struct ResourceChange {
+    pub before: Box<dyn Reflect>,
+    pub after: Box<dyn Reflect>,
+}
+
+pub enum EditAction {
+    Components(Vec<ComponentChange>),
+    Resource(ResourceChange),
+    Spawn(Vec<CarRecord>),
+    Despawn(Vec<CarRecord>),
+}
+
+pub struct Edit {
+    pub label: String,
+    pub action: EditAction,
+}
+
+impl Edit {
+    pub fn new(label: impl Into<String>, action: EditAction) -> Edit {
+        Edit {
+            label: label.into(),
+            action,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct History {
+    pub undo_stack: Vec<Edit>, // oldest first
+    pub redo_stack: Vec<Edit>, // next to redo last
+}
+
+impl History {
+    pub fn record(&mut self, edit: Edit) {
+        // a new edit branches off from here, so anything that was undone can't come back
+        debug!("recording edit: {}", edit.label);
+        self.redo_stack.clear();
+        self.undo_stack.push(edit);
+
+        if self.undo_stack.len() > HISTORY_LENGTH {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    // for cars that came back under new ids; see `remap_vehicle_references`
+    pub fn remap_entities(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        let remap = |entity: &mut Entity| {
+            if let Some(mapped) = entity_map.get(entity) {
+                *entity = *mapped;
+            }
+        };
+
+        for edit in self.undo_stack.iter_mut().chain(&mut self.redo_stack) {
+            match &mut edit.action {
+                EditAction::Components(changes) => {
+                    changes
+                        .iter_mut()
+                        .for_each(|change| remap(&mut change.entity));
+                }
+                EditAction::Spawn(records) | EditAction::Despawn(records) => {
+                    records
+                        .iter_mut()
+                        .for_each(|record| remap(&mut record.entity));
+                }
+                EditAction::Resource(_) => {}
+            }
+        }
+    }
+
+    pub fn record_or_merge(&mut self, edit: Edit) {
+        // folds repeated edits of the same thing (e.g. every frame of dragging a value in the
+        // inspector) into the last one, keeping its original `before`
+        if let (
+            Some(Edit {
+                label,
+                action: EditAction::Components(last_changes),
+            }),
+            EditAction::Components(changes),
+        ) = (self.undo_stack.last_mut(), &edit.action)
+        {
+            if *label == edit.label
+                && last_changes.len() == changes.len()
+                && self.redo_stack.is_empty()
+            {
+                for (last_change, change) in last_changes.iter_mut().zip(changes) {
+                    last_change.after = change.after.as_ref().map(|after| after.clone_value());
+                }
+                return;
+            }
+        }
+
+        self.record(edit);
+    }
+}
+
+pub fn undo(world: &mut World) -> bool {
+    let Some(mut edit) = world.resource_mut::<History>().undo_stack.pop() else {
+        return false;
+    };
+
+    info!("undo: {}", edit.label);
+    run_action(world, &mut edit.action, false);
+    world.resource_mut::<History>().redo_stack.push(edit);
+
+    true
+}
+
+pub fn redo(world: &mut World) -> bool {
+    let Some(mut edit) = world.resource_mut::<History>().redo_stack.pop() else {
+        return false;
+    };
+
+    info!("redo: {}", edit.label);
+    run_action(world, &mut edit.action, true);
+    world.resource_mut::<History>().undo_stack.push(edit);
+
+    true
+}
+
+pub fn jump_to(world: &mut World, undo_len: usize) {
+    // undoes or redoes until exactly `undo_len` edits are applied
+    while world.resource::<History>().undo_stack.len() > undo_len && undo(world) {}
+    while world.resource::<History>().undo_stack.len() < undo_len && redo(world) {}
+}
+
+fn run_action(world: &mut World, action: &mut EditAction, forward: bool) {
+    match action {
+        EditAction::Components(changes) => {
+            // reverted in the opposite order they were made, in case an edit touched one
+            // component twice
+            if forward {
+                changes
+                    .iter()
+                    .for_each(|change| set_component(world, change, forward));
+            } else {
+                changes
+                    .iter()
+                    .rev()
+                    .for_each(|change| set_component(world, change, forward));
+            }
+        }
+        EditAction::Resource(change) => {
+            let value = if forward {
+                change.after.as_ref()
+            } else {
+                change.before.as_ref()
+            };
+            set_resource(world, value);
+        }
+        EditAction::Spawn(records) if forward => respawn_cars(world, records),
+        EditAction::Spawn(records) => records
+            .iter_mut()
+            .for_each(|record| despawn_car(world, record)),
+        EditAction::Despawn(records) if forward => records
+            .iter_mut()
+            .for_each(|record| despawn_car(world, record)),
+        EditAction::Despawn(records) => respawn_cars(world, records),
+    }
+}
+
+fn set_component(world: &mut World, change: &ComponentChange, forward: bool) {
+    let value = if forward {
+        change.after.as_deref()
+    } else {
+        change.before.as_deref()
+    };
+
+    let Some(type_info) = change
+        .before
+        .as_deref()
+        .or(change.after.as_deref())
+        .and_then(|value| value.get_represented_type_info())
+    else {
+        return;
+    };
+
+    let type_registry = world.resource::<AppTypeRegistry>().clone();
+    let type_registry = type_registry.read();
+
+    let Some(reflect_component) = type_registry
+        .get(type_info.type_id())
+        .and_then(|registration| registration.data::<ReflectComponent>())
+    else {
+        warn!("{} is not a reflected component", type_info.type_path());
+        return;
+    };
+
+    let Some(mut entity) = world.get_entity_mut(change.entity) else {
+        warn!("{:?} no longer exists", change.entity);
+        return;
+    };
+
+    match value {
+        Some(value) => reflect_component.apply_or_insert(&mut entity, value, &type_registry),
+        None => reflect_component.remove(&mut entity),
+    }
+}
+
+fn set_resource(world: &mut World, value: &dyn Reflect) {
+    let Some(type_info) = value.get_represented_type_info() else {
+        return;
+    };
+
+    let type_registry = world.resource::<AppTypeRegistry>().clone();
+    let type_registry = type_registry.read();
+
+    let Some(reflect_resource) = type_registry
+        .get(type_info.type_id())
+        .and_then(|registration| registration.data::<ReflectResource>())
+    else {
+        warn!("{} is not a reflected resource", type_info.type_path());
+        return;
+    };
+
+    reflect_resource.apply(world, value);
+}
+
+pub fn despawn_car(world: &mut World, record: &mut CarRecord) {
+    record.snapshot = CarSnapshot::capture(world, record.entity);
+
+    if let Some(entity) = world.get_entity_mut(record.entity) {
+        entity.despawn_recursive();
+    }
+}
+
+// the cars come back under fresh ids rather than their old ones, which something else may have
+// been given since; anything that referred to them by their old ids is pointed at the new
+pub fn respawn_cars(world: &mut World, records: &mut [CarRecord]) {
+    let entity_map = reserve_vehicle_ids(
+        world,
+        records
+            .iter()
+            .filter(|record| record.snapshot.is_some())
+            .map(|record| record.entity),
+    );
+
+    for record in records.iter_mut() {
+        let Some(snapshot) = &record.snapshot else {
+            continue;
+        };
+
+        let entity = entity_map[&record.entity];
+        let mut entity_mut = world.entity_mut(entity);
+        entity_mut.insert(pickable_car(snapshot.to_bundle()));
+        if snapshot.frozen {
+            entity_mut.insert(Frozen);
+        }
+
+        record.entity = entity;
+    }
+
+    remap_vehicle_references(world, &entity_map);
+}
@@ -0,0 +1,23 @@
+// bevy system signatures routinely trip these
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+
+// the simulation itself, shared by the windowed app and the batch runner
+
+pub mod components;
+pub mod constants;
+pub mod events;
+pub mod file_format;
+pub mod headless;
+pub mod history;
+pub mod profiling;
+pub mod replay;
+pub mod resources;
+pub mod rewind;
+pub mod save;
+pub mod scenario;
+pub mod simulation;
+pub mod snapshot;
+pub mod stepping;
+pub mod systems;
+pub mod ui;
+pub mod util;
@@ -1,39 +1,47 @@
-use std::any::{Any, TypeId};
-use std::collections::HashSet;
+// bevy system signatures routinely trip these
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
 
-use crate::bevy_egui::{
-    egui::{self, ScrollArea},
-    EguiContexts, EguiPlugin,
-};
+use crate::bevy_egui::EguiPlugin;
 
-use bevy::{
-    ecs::component::ComponentInfo, log::LogPlugin, prelude::*, sprite::MaterialMesh2dBundle,
-    window::*,
-};
+use bevy::{log::LogPlugin, prelude::*, sprite::MaterialMesh2dBundle, window::*};
 
 use bevy_picking_egui::*;
 
 use bevy_mod_picking::prelude::*;
 
-pub mod components;
-pub mod constants;
-pub mod events;
-pub mod resources;
-pub mod stepping;
-pub mod systems;
-pub mod util;
+use traffic::{events, headless, stepping, systems, ui};
 
-use crate::components::*;
-use crate::constants::*;
-use crate::events::*;
-use crate::resources::*;
-use crate::util::*;
+use traffic::components::*;
+use traffic::constants::*;
+use traffic::events::*;
+use traffic::history::*;
+use traffic::profiling::*;
+use traffic::replay::*;
+use traffic::resources::*;
+use traffic::rewind::*;
+use traffic::simulation::*;
+use traffic::util::*;
 
 // We can create our own gizmo config group!
 #[derive(Default, Reflect, GizmoConfigGroup)]
 struct MyRoundGizmos {}
 
 fn main() {
+    let args = parse_args();
+
+    if let Some(seconds) = args.headless {
+        if let Err(message) = headless::run_headless(
+            seconds,
+            args.cars,
+            args.save_slot,
+            args.check_invariants,
+            args.profile,
+        ) {
+            exit_with_usage(&message);
+        }
+        return;
+    }
+
     App::new()
         /////////////
         // PLUGINS //
@@ -44,6 +52,7 @@ fn main() {
                     primary_window: Some(Window {
                         resolution: WindowResolution::new(WINDOW_WIDTH, WINDOW_HEIGHT)
                             .with_scale_factor_override(1.),
+                        resizable: true,
                         ..default()
                     }),
                     ..default()
@@ -55,6 +64,7 @@ fn main() {
             EguiPlugin,
             EguiBackend,
             DefaultPickingPlugins,
+            SimulationPlugin::default(),
         ))
         .add_plugins(
             stepping::SteppingPlugin::default()
@@ -72,51 +82,113 @@ fn main() {
             cars_to_spawn: vec![],
         })
         .init_resource::<CursorWorldCoords>()
+        .init_resource::<History>()
+        .init_resource::<VehicleMaterials>()
+        .init_resource::<PlotsConfig>()
+        .init_resource::<TrajectoryViewConfig>()
+        .init_resource::<VehicleOverlays>()
+        .init_resource::<SimulationSpeed>()
+        .insert_resource(args.save_slot)
+        .insert_resource(InvariantChecks {
+            enabled: args.check_invariants,
+            ..default()
+        })
+        .insert_resource(Rewind {
+            enabled: true,
+            ..default()
+        })
         ////////////
         // STATES //
         ////////////
         .init_state::<DebugState>()
-        .init_state::<PauseState>()
+        .init_state::<CameraMode>()
+        .init_state::<ColorMode>()
         ////////////
         // EVENTS //
         ////////////
         .add_event::<events::CollisionEvent>()
         .add_event::<events::CarSpawnEvent>()
-        .add_event::<SelectEntityEvent>()
-        .add_event::<DeselectEntityEvent>()
         .add_event::<ModifySelectedDriverAgentEvent>()
+        .add_event::<ModifyComponentEvent>()
+        .add_event::<SelectionActionEvent>()
+        .add_event::<HistoryRequestEvent>()
+        .add_event::<ReplayRequestEvent>()
+        .add_event::<RewindRequestEvent>()
+        .add_event::<SaveRequestEvent>()
         /////////////
         // SYSTEMS //
         /////////////
         // .configure_sets(Update, (SomeSet.run_if(in_state(PauseState::Paused))))
-        .add_systems(Startup, setup)
-        .add_systems(
-            FixedUpdate,
-            (
-                systems::collision_system,
-                systems::apply_friction,
-                systems::apply_velocity,
-                systems::wrap_position,
-                systems::agent_check_lane_change_system,
-                systems::agent_active_lane_change_system,
-                systems::agent_drive_system,
-            )
-                .run_if(in_state(PauseState::Running))
-                .chain(),
-        )
+        .add_systems(Startup, (setup, systems::spawn_heatmap_cells))
         .add_systems(
             Update,
             (
-                select_event_listener,
-                deselect_event_listener,
-                modify_entity_driver_agent_listener,
-                systems::draw_car_sight_lines,
-                ui_example,
-                systems::cursor_system,
-                systems::debug_mouse_system,
-                systems::keyboard_input_system,
-                systems::digit_input_system,
-                bevy::window::close_on_esc,
+                profiled(Update, select_event_listener),
+                profiled(Update, deselect_event_listener),
+                // edits would be lost on the recorded cars, so they wait for the live simulation
+                (
+                    profiled(Update, modify_entity_driver_agent_listener),
+                    profiled(Update, systems::modify_component_listener),
+                    profiled(Update, systems::keyboard_input_system),
+                    profiled(Update, systems::selection_action_listener),
+                    profiled(Update, systems::history_request_listener),
+                    profiled(Update, systems::digit_input_system),
+                    profiled(Update, systems::save_request_listener),
+                    profiled(Update, systems::save_input_system),
+                )
+                    .run_if(in_state(SimulationMode::Live)),
+                (
+                    profiled(Update, systems::replay_request_listener),
+                    profiled(Update, replay_playback_system)
+                        .run_if(in_state(SimulationMode::Replay)),
+                    profiled(Update, systems::replay_input_system),
+                ),
+                (
+                    profiled(Update, systems::rewind_request_listener),
+                    profiled(Update, systems::rewind_input_system),
+                )
+                    .run_if(in_state(PauseState::Paused).and_then(in_state(SimulationMode::Live))),
+                profiled(Update, systems::vehicle_color_system),
+                profiled(Update, systems::heatmap_draw_system),
+                (
+                    profiled(Update, systems::draw_car_sight_lines),
+                    profiled(Update, systems::draw_turn_signals),
+                    profiled(Update, systems::draw_perception_ranges),
+                    profiled(Update, systems::draw_vehicle_overlays),
+                ),
+                (
+                    profiled(Update, ui::inspector_ui),
+                    profiled(Update, ui::driver_editor_ui),
+                    profiled(Update, ui::history_ui),
+                    profiled(Update, ui::minimap_ui),
+                    profiled(Update, ui::vehicle_color_legend_ui),
+                    profiled(Update, ui::plots_ui),
+                    profiled(Update, ui::trajectories_ui),
+                    profiled(Update, ui::heatmap_ui),
+                    profiled(Update, ui::vehicle_overlays_ui),
+                    profiled(Update, ui::replay_ui),
+                    profiled(Update, ui::rewind_ui),
+                    profiled(Update, ui::save_ui),
+                    profiled(Update, ui::breakpoints_ui),
+                    profiled(Update, ui::invariants_ui),
+                    profiled(Update, ui::profiler_ui),
+                    profiled(Update, ui::simulation_speed_ui),
+                ),
+                (
+                    profiled(Update, systems::camera_zoom_system),
+                    profiled(Update, systems::camera_pan_system),
+                    profiled(Update, systems::camera_follow_system)
+                        .run_if(in_state(CameraMode::FollowSelected)),
+                    profiled(Update, systems::camera_fit_system)
+                        .run_if(in_state(CameraMode::FitToRoad)),
+                )
+                    .chain(),
+                profiled(Update, systems::cursor_system),
+                profiled(Update, systems::debug_mouse_system),
+                profiled(Update, systems::box_select_system),
+                profiled(Update, systems::view_input_system),
+                profiled(Update, systems::simulation_speed_system),
+                profiled(Update, bevy::window::close_on_esc),
                 // systems::mouse_click_system,
                 // update_scoreboard,
                 // draw_example_collection,
@@ -127,6 +199,68 @@ fn main() {
         .run();
 }
 
+struct Args {
+    save_slot: SaveSlot,
+    headless: Option<f32>, // simulated seconds to run for without a window
+    cars: usize,
+    check_invariants: bool,
+    profile: bool, // time every system and print the report after a headless run
+}
+
+const USAGE: &str = "usage: traffic [--load <file>] [--check-invariants] \
+                     [--headless <seconds> [--cars <count>] [--profile]]";
+
+fn parse_args() -> Args {
+    let mut parsed = Args {
+        save_slot: SaveSlot::default(),
+        headless: None,
+        cars: HEADLESS_CARS,
+        check_invariants: false,
+        profile: false,
+    };
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == "--check-invariants" {
+            parsed.check_invariants = true;
+            continue;
+        }
+        if arg == "--profile" {
+            parsed.profile = true;
+            continue;
+        }
+
+        if !matches!(arg.as_str(), "--load" | "--headless" | "--cars") {
+            exit_with_usage(&format!("unknown argument {arg}"));
+        }
+        let Some(value) = args.next() else {
+            exit_with_usage(&format!("{arg} needs a value"));
+        };
+
+        match arg.as_str() {
+            "--load" => {
+                parsed.save_slot.path = value;
+                parsed.save_slot.load_on_startup = true;
+            }
+            "--headless" => parsed.headless = Some(parse_number(&arg, &value)),
+            _ => parsed.cars = parse_number(&arg, &value),
+        }
+    }
+
+    parsed
+}
+
+fn parse_number<T: std::str::FromStr>(arg: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| exit_with_usage(&format!("{arg} needs a number, not {value}")))
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{message}\n{USAGE}");
+    std::process::exit(2);
+}
+
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -157,20 +291,22 @@ fn setup(
         0,
         &mut commands,
         meshes.add(Rectangle::default()).into(),
-        materials.add(ColorMaterial::from(CAR_COLOR)),
+        Handle::default(),
         DriverLawfulness::Orderly,
         DriverTemperament::Passive,
         DriverPatience::Normal,
+        DriverAttentiveness::Normal,
     );
 
     spawn_car_at_lane(
         1,
         &mut commands,
         meshes.add(Rectangle::default()).into(),
-        materials.add(ColorMaterial::from(CAR_COLOR)),
+        Handle::default(),
         DriverLawfulness::Orderly,
         DriverTemperament::Aggressive,
         DriverPatience::Normal,
+        DriverAttentiveness::Normal,
     );
 
     // Scoreboard
@@ -274,11 +410,12 @@ fn spawn_lanes(commands: &mut Commands) {
     }
 }
 
+#[allow(dead_code)]
 fn draw_example_collection(
     mut gizmos: Gizmos,
-    mut my_gizmos: Gizmos<MyRoundGizmos>,
-    time: Res<Time>,
-    q_windows: Query<&Window, With<PrimaryWindow>>,
+    _my_gizmos: Gizmos<MyRoundGizmos>,
+    _time: Res<Time>,
+    _q_windows: Query<&Window, With<PrimaryWindow>>,
     cursor_coords: ResMut<CursorWorldCoords>,
 ) {
     // let sin = time.elapsed_seconds().sin() * 50.;
@@ -367,6 +504,7 @@ fn draw_example_collection(
     // );
 }
 
+#[allow(dead_code)]
 fn update_config(
     mut config_store: ResMut<GizmoConfigStore>,
     keyboard: Res<ButtonInput<KeyCode>>,
@@ -416,169 +554,37 @@ fn deselect_event_listener(mut reader: EventReader<DeselectEntityEvent>, mut com
 fn modify_entity_driver_agent_listener(
     mut reader: EventReader<ModifySelectedDriverAgentEvent>,
     mut query: Query<(Entity, &mut DriverAgent), With<SelectedEntity>>,
+    mut history: ResMut<History>,
 ) {
     for event in reader.read() {
-        info!("Got request to modify entity");
-        if let Ok(mut agent) = query.get_single_mut() {
-            info!("Doing it!");
-            agent.1.driver_state = event.0.driver_state.clone();
-            agent.1.lawfulness = event.0.lawfulness.clone();
-            agent.1.temperament = event.0.temperament.clone();
-            agent.1.patience = event.0.patience.clone();
-        }
-    }
-}
-
-fn debug_ui_no_entity(egui_contexts: &mut EguiContexts) {
-    egui::Window::new("Entity Editor").show(egui_contexts.ctx_mut(), |ui| {
-        ScrollArea::both().auto_shrink([false; 2]).show(ui, |ui| {
-            ui.heading("Please select an entity!");
-        })
-    });
-}
-
-fn debug_ui_with_entity(
-    egui_contexts: &mut EguiContexts,
-    entity: Entity,
-    driver_agent: &DriverAgent,
-    modify_entity_writer: &mut EventWriter<ModifySelectedDriverAgentEvent>,
-) {
-    egui::Window::new("Entity Editor").show(egui_contexts.ctx_mut(), |ui| {
-        ScrollArea::both().auto_shrink([false; 2]).show(ui, |ui| {
-            ui.heading(format!("You have selected entity {:?}!", entity));
-            ui.horizontal(|ui| {
-                if ui
-                    .add(egui::RadioButton::new(
-                        driver_agent.temperament == DriverTemperament::Passive,
-                        "Passive",
-                    ))
-                    .clicked()
-                {
-                    info!("Sending passive!");
-                    let new_driver_agent = driver_agent
-                        .clone()
-                        .with_temperament(DriverTemperament::Passive);
-
-                    modify_entity_writer.send(ModifySelectedDriverAgentEvent(new_driver_agent));
+        info!("Modifying selected drivers: {:?}", event.0);
+        let mut changes = vec![];
+
+        for (entity, mut agent) in &mut query {
+            let before = agent.clone();
+            *agent = match event.0.clone() {
+                DriverAgentEdit::Lawfulness(lawfulness) => {
+                    before.clone().with_lawfulness(lawfulness)
                 }
-
-                if ui
-                    .add(egui::RadioButton::new(
-                        driver_agent.temperament == DriverTemperament::Calm,
-                        "Calm",
-                    ))
-                    .clicked()
-                {
-                    info!("Sending calm!");
-                    let new_driver_agent = driver_agent
-                        .clone()
-                        .with_temperament(DriverTemperament::Calm);
-
-                    modify_entity_writer.send(ModifySelectedDriverAgentEvent(new_driver_agent));
-                }
-
-                if ui
-                    .add(egui::RadioButton::new(
-                        driver_agent.temperament == DriverTemperament::Aggressive,
-                        "Aggressive",
-                    ))
-                    .clicked()
-                {
-                    info!("Sending aggressive!");
-                    let new_driver_agent = driver_agent
-                        .clone()
-                        .with_temperament(DriverTemperament::Aggressive);
-
-                    modify_entity_writer.send(ModifySelectedDriverAgentEvent(new_driver_agent));
-                }
-
-                if ui
-                    .add(egui::RadioButton::new(
-                        driver_agent.temperament == DriverTemperament::Psychotic,
-                        "Psychotic",
-                    ))
-                    .clicked()
-                {
-                    info!("Sending psychotic!");
-                    let new_driver_agent = driver_agent
-                        .clone()
-                        .with_temperament(DriverTemperament::Psychotic);
-
-                    modify_entity_writer.send(ModifySelectedDriverAgentEvent(new_driver_agent));
+                DriverAgentEdit::Temperament(temperament) => {
+                    before.clone().with_temperament(temperament)
                 }
+                DriverAgentEdit::Patience(patience) => before.clone().with_patience(patience),
+                DriverAgentEdit::Profile(profile) => before.clone().with_profile(profile),
+            };
+
+            changes.push(ComponentChange {
+                entity,
+                before: Some(before.clone_value()),
+                after: Some(agent.clone_value()),
             });
-        });
-    });
-}
-
-fn ui_example(
-    mut egui_contexts: EguiContexts,
-    mut number: Local<f32>,
-    pause_state: Res<State<PauseState>>,
-    debug_state: Res<State<DebugState>>,
-    // query gets all components of the selected entity for display / modification
-    query: Query<(Entity, &DriverAgent), With<SelectedEntity>>,
-    mut modify_entity_writer: EventWriter<ModifySelectedDriverAgentEvent>,
-    // world: &World,
-) {
-    // debug window shows when paused and debug mode is on
-    if pause_state.get() != &PauseState::Paused || debug_state.get() != &DebugState::Enabled {
-        return;
-    }
+        }
 
-    if let Ok(entity) = query.get_single() {
-        debug_ui_with_entity(
-            &mut egui_contexts,
-            entity.0,
-            entity.1,
-            &mut modify_entity_writer,
-        );
-    } else {
-        debug_ui_no_entity(&mut egui_contexts);
+        if !changes.is_empty() {
+            history.record(Edit::new(
+                format!("{:?} on {} cars", event.0, changes.len()),
+                EditAction::Components(changes),
+            ));
+        }
     }
-
-    // info!("Hello {:?}, you were selected!", event.0);
-
-    // // if let Some(ent) = world.get_entity_mut(event.0) {
-
-    // for component in components_in_entity {
-    //     info!("Entity {:?} has components {:?}", event.0, component);
-    // }
-
-    // egui::SidePanel::left("Left").show(egui_contexts.ctx_mut(), |ui| {
-    //     ScrollArea::vertical()
-    //         .auto_shrink([false; 2])
-    //         .show(ui, |ui| {
-    //             ui.heading("Note that while a slider is being dragged, the panel is being resized, or the scrollbar is being moved, items in the 3d scene cannot be picked even if the mouse is over them.");
-    //             for _ in 0..100 {
-    //                 ui.add(egui::Slider::new(&mut *number, 0.0..=100.0));
-    //             }
-    //         })
-    // });
-
-    // if let Some(entity) = selected_entity {
-    //     heading_text = format!("You have selected entity {:?}!", entity);
-    // }
 }
-
-// #[derive(PartialEq)]
-// enum Enum { First, Second, Third }
-// let mut my_enum = Enum::First;
-
-// ui.radio_value(&mut my_enum, Enum::First, "First");
-
-// // is equivalent to:
-
-// if ui.add(egui::RadioButton::new(my_enum == Enum::First, "First")).clicked() {
-//     my_enum = Enum::First
-// }
-
-// this was using world to get entity info; took it out because
-// I couldn't add &World to the UI system due to a mutability conflict
-// let components_in_entity = world.inspect_entity(event.0);
-// fn to_names(component_infos: Vec<&ComponentInfo>) -> Vec<&str> {
-//     component_infos
-//         .into_iter()
-//         .map(|component_info| component_info.name()) //.type_id())
-//         .collect()
-// }
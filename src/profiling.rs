@@ -0,0 +1,308 @@
+use bevy::ecs::{
+    archetype::ArchetypeComponentId,
+    component::{ComponentId, Tick},
+    query::Access,
+    schedule::{InternedSystemSet, ScheduleLabel},
+    world::unsafe_world_cell::UnsafeWorldCell,
+};
+use bevy::prelude::*;
+use bevy::utils::get_short_name;
+use std::any::TypeId;
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::components::*;
+use crate::constants::*;
+
+// how long each system takes to run. a system wrapped in `profiled` times every one of its runs
+// into the `Profiler`, which keeps the last `PROFILE_RUNS` of them; the overlay and the headless
+// report both summarize those. nothing is timed until the profiler is enabled, by opening the
+// overlay or running headless with `--profile`, so the wrapper costs next to nothing otherwise
+
+pub struct SystemTimings {
+    pub schedule: String,
+    pub name: String,
+    pub runs: VecDeque<Duration>, // oldest first
+}
+
+pub struct SystemStats {
+    pub schedule: String,
+    pub name: String,
+    pub runs: usize,
+    pub average: Duration,
+    pub p95: Duration,
+    pub max: Duration,
+}
+
+impl SystemTimings {
+    fn stats(&self) -> SystemStats {
+        let mut runs: Vec<Duration> = self.runs.iter().copied().collect();
+        runs.sort();
+
+        let total: Duration = runs.iter().sum();
+        let p95_index = (runs.len() as f32 * 0.95).ceil() as usize;
+
+        SystemStats {
+            schedule: self.schedule.clone(),
+            name: self.name.clone(),
+            runs: runs.len(),
+            average: total.checked_div(runs.len() as u32).unwrap_or_default(),
+            p95: runs
+                .get(p95_index.saturating_sub(1))
+                .copied()
+                .unwrap_or_default(),
+            max: runs.last().copied().unwrap_or_default(),
+        }
+    }
+}
+
+// shared with every profiled system, which can't reach resources while it runs alongside others
+#[derive(Resource, Clone, Default)]
+pub struct Profiler {
+    pub visible: bool,
+    enabled: Arc<AtomicBool>,
+    timings: Arc<Mutex<Vec<SystemTimings>>>,
+}
+
+impl Profiler {
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    // one entry per profiled system, slowest on average first
+    pub fn stats(&self) -> Vec<SystemStats> {
+        let timings = self.timings.lock().unwrap();
+        let mut stats: Vec<SystemStats> = timings.iter().map(SystemTimings::stats).collect();
+        stats.sort_by_key(|system| Reverse(system.average));
+        stats
+    }
+
+    pub fn clear(&self) {
+        for timings in self.timings.lock().unwrap().iter_mut() {
+            timings.runs.clear();
+        }
+    }
+
+    fn register(&self, schedule: String, name: String) -> usize {
+        let mut timings = self.timings.lock().unwrap();
+        timings.push(SystemTimings {
+            schedule,
+            name,
+            runs: VecDeque::new(),
+        });
+        timings.len() - 1
+    }
+
+    fn record(&self, index: usize, duration: Duration) {
+        let mut timings = self.timings.lock().unwrap();
+        let runs = &mut timings[index].runs;
+        runs.push_back(duration);
+        if runs.len() > PROFILE_RUNS {
+            runs.pop_front();
+        }
+    }
+
+    // the same numbers as the overlay, as a plain-text table
+    pub fn report(&self, counts: &EntityCounts, timestep: Duration) -> String {
+        let stats = self.stats();
+        let mut report = String::new();
+
+        let _ = writeln!(
+            report,
+            "{} cars, {} entities; tick budget {:.3} ms",
+            counts.cars,
+            counts.entities,
+            millis(timestep)
+        );
+
+        // every FixedUpdate system runs once per tick, so their averages add up to a tick
+        for (schedule, total) in schedule_totals(&stats) {
+            let per = if schedule == format!("{:?}", FixedUpdate) {
+                "tick"
+            } else {
+                "frame"
+            };
+            let _ = writeln!(report, "{schedule}: {:.3} ms per {per}", millis(total));
+        }
+
+        let _ = writeln!(
+            report,
+            "\n{:<12} {:<40} {:>6} {:>9} {:>9} {:>9}",
+            "schedule", "system", "runs", "avg ms", "p95 ms", "max ms"
+        );
+        for system in &stats {
+            let _ = writeln!(
+                report,
+                "{:<12} {:<40} {:>6} {:>9.3} {:>9.3} {:>9.3}",
+                system.schedule,
+                system.name,
+                system.runs,
+                millis(system.average),
+                millis(system.p95),
+                millis(system.max)
+            );
+        }
+
+        report
+    }
+}
+
+pub struct EntityCounts {
+    pub cars: usize,
+    pub entities: usize,
+}
+
+impl EntityCounts {
+    pub fn count(world: &mut World) -> EntityCounts {
+        EntityCounts {
+            cars: world.query_filtered::<(), With<Car>>().iter(world).count(),
+            entities: world.entities().len() as usize,
+        }
+    }
+}
+
+// the summed averages of each schedule's systems, in the order the schedules first appear
+pub fn schedule_totals(stats: &[SystemStats]) -> Vec<(String, Duration)> {
+    let mut totals: Vec<(String, Duration)> = vec![];
+    for system in stats {
+        match totals
+            .iter_mut()
+            .find(|(schedule, _)| *schedule == system.schedule)
+        {
+            Some((_, total)) => *total += system.average,
+            None => totals.push((system.schedule.clone(), system.average)),
+        }
+    }
+    totals
+}
+
+pub fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.
+}
+
+// times every run of `system` and files it under `schedule` in the `Profiler`
+pub fn profiled<M, S: IntoSystem<(), (), M>>(
+    schedule: impl ScheduleLabel,
+    system: S,
+) -> Profiled<S::System> {
+    Profiled {
+        system: IntoSystem::into_system(system),
+        schedule: format!("{:?}", schedule),
+        slot: None,
+    }
+}
+
+pub struct Profiled<S: System<In = (), Out = ()>> {
+    system: S,
+    schedule: String,
+    slot: Option<(Profiler, usize)>, // filled in when the system is initialized
+}
+
+impl<S: System<In = (), Out = ()>> Profiled<S> {
+    // `None` while the profiler is off, so the run goes untimed
+    fn start(&self) -> Option<Instant> {
+        self.slot
+            .as_ref()
+            .filter(|(profiler, _)| profiler.enabled())
+            .map(|_| Instant::now())
+    }
+
+    fn record(&self, start: Option<Instant>) {
+        if let (Some((profiler, index)), Some(start)) = (&self.slot, start) {
+            profiler.record(*index, start.elapsed());
+        }
+    }
+}
+
+// everything but running is handed straight to the wrapped system
+impl<S: System<In = (), Out = ()>> System for Profiled<S> {
+    type In = ();
+    type Out = ();
+
+    fn name(&self) -> Cow<'static, str> {
+        self.system.name()
+    }
+
+    fn type_id(&self) -> TypeId {
+        self.system.type_id()
+    }
+
+    fn component_access(&self) -> &Access<ComponentId> {
+        self.system.component_access()
+    }
+
+    fn archetype_component_access(&self) -> &Access<ArchetypeComponentId> {
+        self.system.archetype_component_access()
+    }
+
+    fn is_send(&self) -> bool {
+        self.system.is_send()
+    }
+
+    fn is_exclusive(&self) -> bool {
+        self.system.is_exclusive()
+    }
+
+    fn has_deferred(&self) -> bool {
+        self.system.has_deferred()
+    }
+
+    unsafe fn run_unsafe(&mut self, input: (), world: UnsafeWorldCell) {
+        let start = self.start();
+        // SAFETY: the caller upholds the wrapped system's requirements, since every access
+        // the wrapper reports is the wrapped system's
+        unsafe { self.system.run_unsafe(input, world) };
+        self.record(start);
+    }
+
+    fn run(&mut self, input: (), world: &mut World) {
+        // exclusive systems only implement this one
+        let start = self.start();
+        self.system.run(input, world);
+        self.record(start);
+    }
+
+    fn apply_deferred(&mut self, world: &mut World) {
+        self.system.apply_deferred(world);
+    }
+
+    fn initialize(&mut self, world: &mut World) {
+        self.system.initialize(world);
+
+        if self.slot.is_none() {
+            let profiler = world.get_resource_or_insert_with(Profiler::default).clone();
+            let index =
+                profiler.register(self.schedule.clone(), get_short_name(&self.system.name()));
+            self.slot = Some((profiler, index));
+        }
+    }
+
+    fn update_archetype_component_access(&mut self, world: UnsafeWorldCell) {
+        self.system.update_archetype_component_access(world);
+    }
+
+    fn check_change_tick(&mut self, change_tick: Tick) {
+        self.system.check_change_tick(change_tick);
+    }
+
+    fn default_system_sets(&self) -> Vec<InternedSystemSet> {
+        self.system.default_system_sets()
+    }
+
+    fn get_last_run(&self) -> Tick {
+        self.system.get_last_run()
+    }
+
+    fn set_last_run(&mut self, last_run: Tick) {
+        self.system.set_last_run(last_run);
+    }
+}
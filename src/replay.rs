@@ -0,0 +1,337 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::file_format::*;
+use crate::history::*;
+use crate::util::*;
+
+// recording and playback of whole runs. a recording holds every vehicle's state after each
+// simulation tick, starting with the state the recording began in, so playing it back shows
+// exactly what happened even after the simulation code has changed. states are rounded to
+// `REPLAY_RESOLUTION` to keep them small, and a recording stops itself after
+// `REPLAY_MAX_LENGTH`. while a recording plays, the simulation systems are off and the live cars
+// are put aside until the replay is closed
+
+// bumped whenever the layout of `Recording` changes; older files are refused rather than misread
+pub const REPLAY_FORMAT_VERSION: u32 = 2;
+const REPLAY_MAGIC: [u8; 4] = *b"TRPL";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct VehicleFrame {
+    pub vehicle: u32, // numbered in the order vehicles first appear in the recording
+    pub position: [i16; 2], // in steps of `REPLAY_RESOLUTION`
+    pub velocity: [i16; 2], // likewise
+    pub lane: i8,
+    pub driver_state: DriverState,
+    pub vehicle_class: VehicleClass,
+    pub turn_signal: LaneChangeDirection,
+}
+
+impl VehicleFrame {
+    pub fn position(&self) -> Vec2 {
+        Vec2::new(unquantize(self.position[0]), unquantize(self.position[1]))
+    }
+
+    pub fn velocity(&self) -> Vec2 {
+        Vec2::new(unquantize(self.velocity[0]), unquantize(self.velocity[1]))
+    }
+}
+
+// anything past the range of an `i16` is clamped to it, which is far beyond the road
+fn quantize(value: f32) -> i16 {
+    (value / REPLAY_RESOLUTION).round() as i16
+}
+
+fn unquantize(value: i16) -> f32 {
+    value as f32 * REPLAY_RESOLUTION
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ReplayFrame {
+    pub vehicles: Vec<VehicleFrame>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Recording {
+    pub timestep: f32,            // simulated seconds per frame
+    pub frames: Vec<ReplayFrame>, // one per tick, the first at time zero
+}
+
+impl Recording {
+    pub fn duration(&self) -> f32 {
+        self.frames.len().saturating_sub(1) as f32 * self.timestep
+    }
+
+    // the frame nearest `time`
+    pub fn frame_at(&self, time: f32) -> Option<&ReplayFrame> {
+        let index = (time / self.timestep).round().max(0.) as usize;
+        self.frames
+            .get(index.min(self.frames.len().saturating_sub(1)))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        write_versioned(path, REPLAY_MAGIC, REPLAY_FORMAT_VERSION, self)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Recording> {
+        read_versioned(path, REPLAY_MAGIC, REPLAY_FORMAT_VERSION)
+    }
+}
+
+// the run being recorded, if any
+#[derive(Resource, Default)]
+pub struct Recorder {
+    pub recording: Option<Recording>,
+    vehicles: HashMap<Entity, u32>, // the number each car is recorded under
+}
+
+#[derive(Resource)]
+pub struct Replay {
+    pub recording: Recording,
+    pub time: f32,                      // playback position, in simulated seconds
+    pub speed: f32,                     // simulated seconds per real second
+    pub vehicles: HashMap<u32, Entity>, // the cars standing in for each recorded vehicle
+    stashed: Vec<CarRecord>,            // the live cars, put back when the replay is closed
+}
+
+pub fn record_frame_system(
+    mut recorder: ResMut<Recorder>,
+    query: Query<
+        (
+            Entity,
+            &Transform,
+            &Velocity,
+            &LaneEntity,
+            &DriverAgent,
+            &VehicleClass,
+            Option<&TurnSignal>,
+        ),
+        With<Car>,
+    >,
+) {
+    let Recorder {
+        recording: Some(recording),
+        vehicles: numbers,
+    } = &mut *recorder
+    else {
+        return;
+    };
+
+    let vehicles = query
+        .iter()
+        .map(
+            |(entity, transform, velocity, lane, agent, vehicle_class, turn_signal)| {
+                let next_number = numbers.len() as u32;
+                VehicleFrame {
+                    vehicle: *numbers.entry(entity).or_insert(next_number),
+                    position: [
+                        quantize(transform.translation.x),
+                        quantize(transform.translation.y),
+                    ],
+                    velocity: [quantize(velocity.x), quantize(velocity.y)],
+                    lane: lane.0 as i8,
+                    driver_state: agent.driver_state,
+                    vehicle_class: *vehicle_class,
+                    turn_signal: turn_signal
+                        .map_or(LaneChangeDirection::None, |signal| signal.direction.clone()),
+                }
+            },
+        )
+        .collect();
+
+    recording.frames.push(ReplayFrame { vehicles });
+
+    if recording.duration() >= REPLAY_MAX_LENGTH {
+        info!("recording reached its limit of {REPLAY_MAX_LENGTH}s");
+        if let Some(recording) = recorder.recording.take() {
+            save_recording(&recording);
+        }
+    }
+}
+
+pub fn start_recording(world: &mut World) {
+    let timestep = world.resource::<Time<Fixed>>().timestep().as_secs_f32();
+
+    *world.resource_mut::<Recorder>() = Recorder {
+        recording: Some(Recording {
+            timestep,
+            frames: Vec::new(),
+        }),
+        vehicles: HashMap::default(),
+    };
+    info!("recording started");
+}
+
+pub fn stop_recording(world: &mut World) {
+    if let Some(recording) = world.resource_mut::<Recorder>().recording.take() {
+        save_recording(&recording);
+    }
+}
+
+fn save_recording(recording: &Recording) {
+    match recording.save(RECORDING_PATH) {
+        Ok(()) => info!(
+            "saved {} frames ({:.1}s) to {RECORDING_PATH}",
+            recording.frames.len(),
+            recording.duration()
+        ),
+        Err(error) => error!("couldn't save recording to {RECORDING_PATH}: {error}"),
+    }
+}
+
+pub fn open_replay(world: &mut World) {
+    if world.contains_resource::<Replay>() {
+        return;
+    }
+
+    let recording = match Recording::load(RECORDING_PATH) {
+        Ok(recording) => recording,
+        Err(error) => {
+            error!("couldn't open recording {RECORDING_PATH}: {error}");
+            return;
+        }
+    };
+
+    // a recording can't play back into itself
+    stop_recording(world);
+
+    let live_cars: Vec<Entity> = world
+        .query_filtered::<Entity, With<Car>>()
+        .iter(world)
+        .collect();
+
+    let stashed = live_cars
+        .into_iter()
+        .map(|entity| {
+            let mut record = CarRecord::spawned(entity);
+            despawn_car(world, &mut record);
+            record
+        })
+        .collect();
+
+    world.insert_resource(Replay {
+        recording,
+        time: 0.,
+        speed: 1.,
+        vehicles: HashMap::default(),
+        stashed,
+    });
+    world
+        .resource_mut::<NextState<SimulationMode>>()
+        .set(SimulationMode::Replay);
+    world
+        .resource_mut::<NextState<PauseState>>()
+        .set(PauseState::Paused);
+}
+
+pub fn close_replay(world: &mut World) {
+    let Some(mut replay) = world.remove_resource::<Replay>() else {
+        return;
+    };
+
+    for entity in replay.vehicles.values() {
+        if let Some(entity) = world.get_entity_mut(*entity) {
+            entity.despawn_recursive();
+        }
+    }
+
+    respawn_cars(world, &mut replay.stashed);
+
+    world
+        .resource_mut::<NextState<SimulationMode>>()
+        .set(SimulationMode::Live);
+}
+
+pub fn replay_playback_system(
+    mut commands: Commands,
+    replay: Option<ResMut<Replay>>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    real_time: Res<Time<Real>>, // the replay has its own speed, apart from the simulation's
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query: Query<
+        (
+            &mut Transform,
+            &mut Velocity,
+            &mut LaneEntity,
+            &mut DriverAgent,
+            &mut VehicleClass,
+        ),
+        With<Car>,
+    >,
+) {
+    // closing the replay removes it a frame before the mode switches back
+    let Some(mut replay) = replay else {
+        return;
+    };
+    let replay = &mut *replay;
+
+    if pause_state.get() == &PauseState::Running {
+        replay.time += real_time.delta_seconds() * replay.speed;
+
+        if replay.time >= replay.recording.duration() {
+            replay.time = replay.recording.duration();
+            next_pause_state.set(PauseState::Paused);
+        }
+    }
+
+    let Some(frame) = replay.recording.frame_at(replay.time) else {
+        return;
+    };
+
+    let mut present = HashSet::new();
+
+    for vehicle in &frame.vehicles {
+        present.insert(vehicle.vehicle);
+        let position = vehicle.position().extend(0.);
+
+        let entity = *replay.vehicles.entry(vehicle.vehicle).or_insert_with(|| {
+            spawn_car(
+                &mut commands,
+                CarBundle::new(
+                    position,
+                    meshes.add(Rectangle::default()).into(),
+                    Handle::default(), // painted by `vehicle_color_system`
+                ),
+            )
+        });
+
+        if let Ok((mut transform, mut velocity, mut lane, mut agent, mut vehicle_class)) =
+            query.get_mut(entity)
+        {
+            transform.translation = position;
+            velocity.0 = vehicle.velocity();
+            lane.0 = vehicle.lane as i32;
+            agent.driver_state = vehicle.driver_state;
+            *vehicle_class = vehicle.vehicle_class;
+        }
+
+        match vehicle.turn_signal {
+            LaneChangeDirection::None => {
+                commands.entity(entity).remove::<TurnSignal>();
+            }
+            ref direction => {
+                commands.entity(entity).insert(TurnSignal {
+                    direction: direction.clone(),
+                    lane_target: vehicle.lane as i32,
+                    elapsed: replay.time,
+                    checked_blind_spot: false,
+                });
+            }
+        }
+    }
+
+    // vehicles that aren't in this frame yet, or any more
+    replay.vehicles.retain(|id, entity| {
+        if present.contains(id) {
+            return true;
+        }
+        commands.entity(*entity).despawn_recursive();
+        false
+    });
+}
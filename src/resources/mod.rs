@@ -1,7 +1,14 @@
 use bevy::math::Vec2;
 use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 
 use crate::components::CarBundle;
+use crate::constants::*;
+use crate::util::*;
 
 #[derive(Resource)]
 pub struct CarSpawnRequests {
@@ -19,3 +26,478 @@ pub struct CollisionSound(pub Handle<AudioSource>);
 pub struct Scoreboard {
     pub score: usize,
 }
+
+// running totals of how merges play out, for measuring cooperation between drivers
+#[derive(Resource, Default, Debug, Clone, Serialize, Deserialize)]
+pub struct MergeCooperationStats {
+    pub signals: usize,
+    pub gaps_opened: usize,
+    pub gaps_ignored: usize,
+    pub gaps_closed: usize,
+    pub merges: usize,
+    pub abandoned: usize,
+}
+
+// the only source of randomness in the simulation, so a run can be reproduced from its seed
+#[derive(Resource)]
+pub struct SimulationRng(pub ChaCha8Rng);
+
+impl Default for SimulationRng {
+    fn default() -> Self {
+        SimulationRng(ChaCha8Rng::seed_from_u64(SIMULATION_SEED))
+    }
+}
+
+// reach of each car's sensors; see `perception_system`
+#[derive(Resource, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Resource)]
+pub struct PerceptionConfig {
+    pub forward_range: f32,
+    pub rear_range: f32,
+    pub side_range: f32, // lateral reach from the car's center, exclusive; reaches adjacent lanes
+    pub blind_spot_length: f32,
+}
+
+impl Default for PerceptionConfig {
+    fn default() -> Self {
+        PerceptionConfig {
+            forward_range: CAR_SIGHT_DISTANCE,
+            rear_range: CAR_REAR_SIGHT_DISTANCE,
+            side_range: LANE_WIDTH * 1.5,
+            blind_spot_length: CAR_BLIND_SPOT_LENGTH,
+        }
+    }
+}
+
+// options for the car-following logic in `agent_drive_system`
+#[derive(Resource, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Resource)]
+pub struct CarFollowingConfig {
+    pub anticipation: bool, // react to slowdowns past the immediate leader; A toggles
+    pub anticipation_depth: usize, // vehicles ahead kept by `collision_system`, leader included
+}
+
+impl Default for CarFollowingConfig {
+    fn default() -> Self {
+        CarFollowingConfig {
+            anticipation: false,
+            anticipation_depth: ANTICIPATION_DEPTH,
+        }
+    }
+}
+
+// perception error applied on top of each driver's judgment; see `perceive_distance`
+#[derive(Resource, Clone, Reflect, Serialize, Deserialize)]
+#[reflect(Resource)]
+pub struct PerceptionNoiseConfig {
+    pub enabled: bool, // N toggles
+    pub distance_noise_pct: f32,
+    pub speed_noise: f32,
+    pub correlation: f32,
+}
+
+impl Default for PerceptionNoiseConfig {
+    fn default() -> Self {
+        PerceptionNoiseConfig {
+            enabled: false,
+            distance_noise_pct: PERCEPTION_DISTANCE_NOISE_PCT,
+            speed_noise: PERCEPTION_SPEED_NOISE,
+            correlation: PERCEPTION_ERROR_CORRELATION,
+        }
+    }
+}
+
+// one material per coloring mode and bucket, shared by every car in that bucket; see
+// `vehicle_color_system`
+#[derive(Resource, Default)]
+pub struct VehicleMaterials {
+    pub handles: HashMap<(ColorMode, usize), Handle<ColorMaterial>>,
+}
+
+// simulated time, which only advances while the simulation is running
+#[derive(Resource, Default, Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationClock {
+    pub elapsed: f32, // seconds
+    pub ticks: u64,
+}
+
+// road-wide measurements taken every `METRICS_SAMPLE_PERIOD` of simulated time
+#[derive(Clone, Debug, Default)]
+pub struct MetricsSample {
+    pub time: f32,
+    pub mean_speed: f32,
+    pub density: f32, // cars per 1000 units of lane
+    pub flow: f32,    // cars per minute passing a point, per lane (density × mean speed)
+    pub cars_per_lane: Vec<usize>,
+    pub lane_changes_per_minute: f32,
+    pub crashes: usize, // since the simulation started; the same car can crash again
+}
+
+#[derive(Resource, Default)]
+pub struct TrafficMetrics {
+    pub samples: VecDeque<MetricsSample>, // oldest first, at most `METRICS_HISTORY` seconds
+    pub lane_changes: Vec<(f32, usize)>,  // (time, running total) at each sample
+    pub total_lane_changes: usize,
+    pub total_crashes: usize,
+}
+
+// how the plots panel is shown; see `plots_ui`
+#[derive(Resource)]
+pub struct PlotsConfig {
+    pub visible: bool,
+    pub docked: bool, // docked along the bottom of the window instead of floating
+    pub window_length: f32, // simulated seconds shown
+}
+
+impl Default for PlotsConfig {
+    fn default() -> Self {
+        PlotsConfig {
+            visible: true,
+            docked: false,
+            window_length: PLOT_WINDOW,
+        }
+    }
+}
+
+// one point on a vehicle's path through the space-time diagram
+#[derive(Clone, Debug)]
+pub struct TrajectoryPoint {
+    pub time: f32,
+    pub position: f32, // along the road
+    pub speed: f32,
+    pub lane: i32,
+}
+
+#[derive(Resource, Default)]
+pub struct Trajectories {
+    pub vehicles: HashMap<Entity, VecDeque<TrajectoryPoint>>, // oldest first
+    pub last_sample_time: Option<f32>,
+}
+
+#[derive(Resource)]
+pub struct TrajectoryViewConfig {
+    pub visible: bool,
+    pub lane: i32,
+    pub window_length: f32, // simulated seconds shown
+}
+
+impl Default for TrajectoryViewConfig {
+    fn default() -> Self {
+        TrajectoryViewConfig {
+            visible: false,
+            lane: 0,
+            window_length: TRAJECTORY_WINDOW,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct VehicleOverlays {
+    pub trails: OverlayScope,
+    pub predicted_path: OverlayScope,
+    pub stopping_envelope: OverlayScope,
+    pub trail_length: f32, // simulated seconds
+}
+
+impl Default for VehicleOverlays {
+    fn default() -> Self {
+        VehicleOverlays {
+            trails: OverlayScope::Selected,
+            predicted_path: OverlayScope::Selected,
+            stopping_envelope: OverlayScope::Selected,
+            trail_length: TRAIL_LENGTH,
+        }
+    }
+}
+
+// recent world positions per vehicle, oldest first; unlike `Trajectories` this keeps the
+// sideways position, so lane changes show up in the trail
+#[derive(Resource, Default)]
+pub struct Trails {
+    pub vehicles: HashMap<Entity, VecDeque<(f32, Vec2)>>,
+    pub last_sample_time: Option<f32>,
+}
+
+// exponentially smoothed measurements for one heatmap cell
+#[derive(Clone, Debug, Default)]
+pub struct HeatmapStats {
+    pub speed: f32,   // mean speed of the cars that were in the cell
+    pub density: f32, // cars per 1000 units of lane
+    pub flow: f32,    // cars per minute passing through
+    pub seen: bool,   // whether any car has been in the cell yet; speed means nothing until then
+}
+
+#[derive(Resource)]
+pub struct Heatmap {
+    pub visible: bool,
+    pub metric: HeatmapMetric,
+    pub smoothing_window: f32,         // simulated seconds
+    pub cells: Vec<Vec<HeatmapStats>>, // per lane, per segment from the bottom of the road
+}
+
+impl Default for Heatmap {
+    fn default() -> Self {
+        let num_segments = ((TOP_WALL - BOTTOM_WALL) / HEATMAP_CELL_LENGTH).ceil() as usize;
+
+        Heatmap {
+            visible: false,
+            metric: HeatmapMetric::Speed,
+            smoothing_window: HEATMAP_SMOOTHING_WINDOW,
+            cells: vec![vec![HeatmapStats::default(); num_segments]; NUM_LANES as usize],
+        }
+    }
+}
+
+// how fast simulated time runs against real time; see `simulation_speed_system`. every tick
+// still covers the same fixed timestep, so a run plays out the same at any speed
+#[derive(Resource)]
+pub struct SimulationSpeed {
+    pub step: usize, // index into `SIMULATION_SPEEDS`; one past the end runs as fast as possible
+    pub ticks_per_frame: u32, // while running as fast as possible
+    pub measured: f32, // simulated seconds per real second, smoothed
+    pub last_elapsed: f32, // simulated time at the previous frame, to measure against
+}
+
+impl Default for SimulationSpeed {
+    fn default() -> Self {
+        SimulationSpeed {
+            step: DEFAULT_SPEED_STEP,
+            ticks_per_frame: 1,
+            measured: 0.,
+            last_elapsed: 0.,
+        }
+    }
+}
+
+impl SimulationSpeed {
+    // `None` when running as fast as possible
+    pub fn multiplier(&self) -> Option<f32> {
+        SIMULATION_SPEEDS.get(self.step).copied()
+    }
+
+    pub fn describe(&self) -> String {
+        match self.multiplier() {
+            Some(multiplier) => format!("{multiplier}x"),
+            None => "As fast as possible".to_string(),
+        }
+    }
+
+    pub fn faster(&mut self) {
+        self.step = usize::min(self.step + 1, SIMULATION_SPEEDS.len());
+    }
+
+    pub fn slower(&mut self) {
+        self.step = self.step.saturating_sub(1);
+    }
+}
+
+pub struct InvariantViolation {
+    pub tick: u64,
+    pub time: f32,
+    pub invariant: Invariant,
+    pub entities: Vec<Entity>,
+    pub detail: String,
+}
+
+// opt-in checks of the simulation's own sanity; a violation is reported once when it starts
+// holding, and again only after it has stopped in between
+#[derive(Resource, Default)]
+pub struct InvariantChecks {
+    pub enabled: bool, // off unless `--check-invariants` is given or it's ticked in the panel
+    pub violations: VecDeque<InvariantViolation>, // oldest first, at most `INVARIANT_VIOLATIONS_LENGTH`
+    pub total: usize, // every violation reported this run, including the ones dropped from the list
+    pub active: HashSet<(Invariant, Vec<Entity>)>, // violations reported that still hold
+}
+
+// where the simulation state is saved to and loaded from; see `save`
+#[derive(Resource)]
+pub struct SaveSlot {
+    pub path: String,
+    pub load_on_startup: bool, // set by `--load` on the command line
+}
+
+impl Default for SaveSlot {
+    fn default() -> Self {
+        SaveSlot {
+            path: SAVE_PATH.to_string(),
+            load_on_startup: false,
+        }
+    }
+}
+
+// what makes a breakpoint fire; the gap and deceleration conditions fire once when a car starts
+// meeting them, and again only once it has stopped meeting them in between
+#[derive(Clone, Debug, PartialEq)]
+pub enum BreakCondition {
+    Collision,                  // a car runs into the one ahead
+    LaneChange(Option<Entity>), // this car, or any car, starts moving into another lane
+    GapBelow(f32),              // distance to the car ahead drops below this
+    DecelerationAbove(f32),     // a car slows down harder than this, in speed per second
+    EntersState(DriverState),
+}
+
+impl BreakCondition {
+    pub fn describe(&self) -> String {
+        match self {
+            BreakCondition::Collision => "Collision".to_string(),
+            BreakCondition::LaneChange(Some(entity)) => format!("Lane change by {:?}", entity),
+            BreakCondition::LaneChange(None) => "Lane change by any car".to_string(),
+            BreakCondition::GapBelow(gap) => format!("Gap below {gap:.1}"),
+            BreakCondition::DecelerationAbove(deceleration) => {
+                format!("Deceleration above {deceleration:.0}")
+            }
+            BreakCondition::EntersState(state) => format!("Enters {:?}", state),
+        }
+    }
+}
+
+pub struct Breakpoint {
+    pub condition: BreakCondition,
+    pub enabled: bool,
+    pub active: HashSet<Entity>, // cars currently meeting a gap or deceleration condition
+}
+
+pub struct BreakpointHit {
+    pub time: f32,
+    pub condition: BreakCondition,
+    pub entities: Vec<Entity>, // the car that tripped it first, then any other car involved
+}
+
+#[derive(Resource, Default)]
+pub struct Breakpoints {
+    pub breakpoints: Vec<Breakpoint>,
+    pub hits: VecDeque<BreakpointHit>, // oldest first, at most `BREAKPOINT_HITS_LENGTH`
+    pub tripped: bool, // a breakpoint fired this frame; no more ticks run until resumed
+}
+
+// what a driver decided on one tick and what it was looking at when it did
+#[derive(Clone, Debug)]
+pub struct Decision {
+    pub branch: DecisionBranch,
+    pub inputs: Vec<(&'static str, f32)>,
+}
+
+impl Decision {
+    pub fn new(branch: DecisionBranch, inputs: Vec<(&'static str, f32)>) -> Decision {
+        Decision { branch, inputs }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DecisionRecord {
+    pub time: f32,
+    pub tick: u64,
+    pub decision: Decision,
+    pub acceleration: Option<f32>, // resulting change in forward speed per second; car following only
+}
+
+#[derive(Clone, Debug)]
+pub struct DecisionFilter {
+    pub car_following: bool,
+    pub lane_change: bool,
+    pub branch: Option<DecisionBranch>, // only this branch; `None` for every branch
+}
+
+impl DecisionFilter {
+    pub fn matches(&self, record: &DecisionRecord) -> bool {
+        let kind_shown = match record.decision.branch.kind() {
+            DecisionKind::CarFollowing => self.car_following,
+            DecisionKind::LaneChange => self.lane_change,
+        };
+
+        kind_shown
+            && self
+                .branch
+                .is_none_or(|branch| branch == record.decision.branch)
+    }
+}
+
+// the decisions each driver made over the last `DECISION_TRACE_LENGTH` simulated seconds,
+// oldest first, so a car's behaviour can be traced back to the numbers that caused it
+#[derive(Resource)]
+pub struct DecisionTrace {
+    pub enabled: bool,
+    pub filter: DecisionFilter, // what the inspector shows and exports
+    pub vehicles: HashMap<Entity, VecDeque<DecisionRecord>>,
+}
+
+impl Default for DecisionTrace {
+    fn default() -> Self {
+        DecisionTrace {
+            enabled: false, // the inspector's "Record" checkbox turns it on
+            filter: DecisionFilter {
+                car_following: true,
+                lane_change: true,
+                branch: None,
+            },
+            vehicles: HashMap::default(),
+        }
+    }
+}
+
+impl DecisionTrace {
+    pub fn record(
+        &mut self,
+        entity: Entity,
+        clock: &SimulationClock,
+        decision: Decision,
+        acceleration: Option<f32>,
+    ) {
+        if !self.enabled {
+            return;
+        }
+
+        self.vehicles
+            .entry(entity)
+            .or_default()
+            .push_back(DecisionRecord {
+                time: clock.elapsed,
+                tick: clock.ticks,
+                decision,
+                acceleration,
+            });
+    }
+
+    // drops decisions that have aged out, along with cars that haven't decided anything since
+    pub fn prune(&mut self, now: f32) {
+        let oldest_kept = now - DECISION_TRACE_LENGTH;
+        self.vehicles.retain(|_, records| {
+            while records
+                .front()
+                .is_some_and(|record| record.time < oldest_kept)
+            {
+                records.pop_front();
+            }
+            !records.is_empty()
+        });
+    }
+
+    // the filtered decisions of one car, one per line, with its inputs as `name=value` pairs
+    pub fn to_csv(&self, entity: Entity) -> String {
+        let mut csv = String::from("time,tick,kind,branch,acceleration,inputs\n");
+
+        let records = self.vehicles.get(&entity).into_iter().flatten();
+        for record in records.filter(|record| self.filter.matches(record)) {
+            let inputs: Vec<String> = record
+                .decision
+                .inputs
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect();
+
+            csv.push_str(&format!(
+                "{},{},{:?},{:?},{},{}\n",
+                record.time,
+                record.tick,
+                record.decision.branch.kind(),
+                record.decision.branch,
+                record
+                    .acceleration
+                    .map_or(String::new(), |acceleration| acceleration.to_string()),
+                inputs.join(" "),
+            ));
+        }
+
+        csv
+    }
+}
@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+use crate::constants::*;
+use crate::resources::*;
+use crate::snapshot::*;
+
+// a snapshot of the simulation after every tick of the last `REWIND_LENGTH` simulated seconds.
+// while paused, the world can be moved back and forth through them; resuming from a point in
+// the past carries on from there and throws away everything that came after it. a snapshot of
+// every car each tick isn't cheap, so nothing is kept unless it's enabled, which only the
+// windowed app does
+
+#[derive(Resource, Default)]
+pub struct Rewind {
+    pub enabled: bool,
+    pub snapshots: VecDeque<SimulationSnapshot>, // oldest first; the last is the latest tick
+    pub cursor: Option<usize>, // the snapshot the world was rewound to; `None` at the latest tick
+}
+
+impl Rewind {
+    pub fn position(&self) -> usize {
+        self.cursor
+            .unwrap_or(self.snapshots.len().saturating_sub(1))
+    }
+}
+
+pub fn rewind_resume_system(world: &mut World) {
+    // running again after a rewind: the ticks after the rewound one didn't happen
+    let Some(position) = world.resource_mut::<Rewind>().cursor.take() else {
+        return;
+    };
+
+    world
+        .resource_mut::<Rewind>()
+        .snapshots
+        .truncate(position + 1);
+    discard_history_after(world);
+}
+
+pub fn rewind_enabled(rewind: Res<Rewind>) -> bool {
+    rewind.enabled
+}
+
+pub fn rewind_capture_system(world: &mut World) {
+    // exclusive, since a snapshot reads every car
+    let timestep = world.resource::<Time<Fixed>>().timestep().as_secs_f32();
+    let capacity = (REWIND_LENGTH / timestep).ceil() as usize;
+
+    let snapshot = SimulationSnapshot::capture(world);
+
+    let mut rewind = world.resource_mut::<Rewind>();
+    rewind.snapshots.push_back(snapshot);
+    while rewind.snapshots.len() > capacity {
+        rewind.snapshots.pop_front();
+    }
+}
+
+pub fn rewind_to(world: &mut World, position: usize) {
+    let rewind = world.resource::<Rewind>();
+    let Some(snapshot) = rewind.snapshots.get(position).cloned() else {
+        return;
+    };
+    let latest = rewind.snapshots.len() - 1;
+
+    snapshot.restore(world);
+    world.resource_mut::<Rewind>().cursor = (position < latest).then_some(position);
+}
+
+pub fn step_back(world: &mut World) {
+    let position = world.resource::<Rewind>().position();
+    if position > 0 {
+        rewind_to(world, position - 1);
+    }
+}
+
+pub fn step_forward(world: &mut World) {
+    let position = world.resource::<Rewind>().position();
+    rewind_to(world, position + 1);
+}
+
+// drop the samples the plots, overlays and decision trace took after the tick the simulation resumes from
+fn discard_history_after(world: &mut World) {
+    let now = world.resource::<SimulationClock>().elapsed;
+
+    let mut metrics = world.resource_mut::<TrafficMetrics>();
+    metrics.samples.retain(|sample| sample.time <= now);
+    metrics.lane_changes.retain(|(time, _)| *time <= now);
+    metrics.total_lane_changes = metrics.lane_changes.last().map_or(0, |(_, total)| *total);
+    metrics.total_crashes = metrics.samples.back().map_or(0, |sample| sample.crashes);
+
+    let mut trajectories = world.resource_mut::<Trajectories>();
+    for points in trajectories.vehicles.values_mut() {
+        points.retain(|point| point.time <= now);
+    }
+    trajectories.last_sample_time = None;
+
+    let mut trails = world.resource_mut::<Trails>();
+    for points in trails.vehicles.values_mut() {
+        points.retain(|(time, _)| *time <= now);
+    }
+    trails.last_sample_time = None;
+
+    let mut decision_trace = world.resource_mut::<DecisionTrace>();
+    for records in decision_trace.vehicles.values_mut() {
+        records.retain(|record| record.time <= now);
+    }
+}
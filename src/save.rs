@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::Path;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::file_format::*;
+use crate::history::*;
+use crate::replay::*;
+use crate::resources::*;
+use crate::rewind::*;
+use crate::snapshot::*;
+
+// saving the whole simulation to a file and picking it up again later, in this run or another.
+// a save holds a `SimulationSnapshot` plus everything else the next tick depends on: the
+// tunable configs, the fixed timestep and the road the cars were driving on
+
+// bumped whenever the layout of `SaveFile` or anything in it changes
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+const SAVE_MAGIC: [u8; 4] = *b"TSAV";
+
+// the road is built from constants, so a save only records it to refuse loading onto another
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct RoadLayout {
+    pub num_lanes: i32,
+    pub lane_width: f32,
+    pub bottom: f32,
+    pub top: f32,
+}
+
+impl RoadLayout {
+    pub fn current() -> RoadLayout {
+        RoadLayout {
+            num_lanes: NUM_LANES,
+            lane_width: LANE_WIDTH,
+            bottom: BOTTOM_WALL,
+            top: TOP_WALL,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveFile {
+    pub road: RoadLayout,
+    pub timestep: f64, // seconds per fixed tick
+    pub perception_config: PerceptionConfig,
+    pub car_following_config: CarFollowingConfig,
+    pub perception_noise_config: PerceptionNoiseConfig,
+    pub snapshot: SimulationSnapshot,
+}
+
+impl SaveFile {
+    pub fn capture(world: &mut World) -> SaveFile {
+        SaveFile {
+            road: RoadLayout::current(),
+            timestep: world.resource::<Time<Fixed>>().timestep().as_secs_f64(),
+            perception_config: world.resource::<PerceptionConfig>().clone(),
+            car_following_config: world.resource::<CarFollowingConfig>().clone(),
+            perception_noise_config: world.resource::<PerceptionNoiseConfig>().clone(),
+            snapshot: SimulationSnapshot::capture(world),
+        }
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        write_versioned(path, SAVE_MAGIC, SAVE_FORMAT_VERSION, self)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> io::Result<SaveFile> {
+        read_versioned(path, SAVE_MAGIC, SAVE_FORMAT_VERSION)
+    }
+}
+
+pub fn save_state(world: &mut World, path: &str) {
+    let save_file = SaveFile::capture(world);
+
+    match save_file.write(path) {
+        Ok(()) => info!(
+            "saved {} cars at {:.2}s to {path}",
+            save_file.snapshot.vehicles.len(),
+            save_file.snapshot.clock.elapsed
+        ),
+        Err(error) => error!("couldn't save to {path}: {error}"),
+    }
+}
+
+pub fn load_state(world: &mut World, path: &str) {
+    let save_file = match SaveFile::read(path) {
+        Ok(save_file) => save_file,
+        Err(error) => {
+            error!("couldn't load {path}: {error}");
+            return;
+        }
+    };
+
+    if save_file.road != RoadLayout::current() {
+        error!(
+            "couldn't load {path}: it was saved on a different road ({:?})",
+            save_file.road
+        );
+        return;
+    }
+
+    // a recording would jump between two unrelated runs
+    stop_recording(world);
+
+    world
+        .resource_mut::<Time<Fixed>>()
+        .set_timestep_seconds(save_file.timestep);
+    world.insert_resource(save_file.perception_config);
+    world.insert_resource(save_file.car_following_config);
+    world.insert_resource(save_file.perception_noise_config);
+
+    let current: Vec<Entity> = world
+        .query_filtered::<Entity, With<Car>>()
+        .iter(world)
+        .collect();
+    for entity in current {
+        world.entity_mut(entity).despawn_recursive();
+    }
+
+    // every car comes back under a fresh id, in the same order as the ids it was saved with, so
+    // systems that visit cars in entity order carry on exactly as the saved run would have
+    save_file.snapshot.restore(world);
+
+    // everything recorded about the run so far belongs to the one that was replaced
+    world.insert_resource(History::default());
+    world.insert_resource(Rewind::default());
+    world.insert_resource(TrafficMetrics::default());
+    world.insert_resource(Trajectories::default());
+    world.insert_resource(Trails::default());
+    world.resource_mut::<DecisionTrace>().vehicles.clear();
+
+    info!(
+        "loaded {} cars at {:.2}s from {path}",
+        save_file.snapshot.vehicles.len(),
+        save_file.snapshot.clock.elapsed
+    );
+}
+
+pub fn load_on_startup_system(world: &mut World) {
+    let save_slot = world.resource::<SaveSlot>();
+    if !save_slot.load_on_startup {
+        return;
+    }
+
+    let path = save_slot.path.clone();
+    load_state(world, &path);
+}
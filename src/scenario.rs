@@ -0,0 +1,226 @@
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::constants::*;
+use crate::headless::*;
+use crate::util::*;
+
+// a headless run described in a file rather than on the command line: how many cars, who is
+// driving them, for how long and what should come of it. written in RON, with anything left out
+// taking its default, e.g.
+//
+//     (seconds: 120, warmup: 20, cars: 60, mix: [(Calm, 3), (Aggressive, 1)], seed: 7,
+//      expect: (no_crashes: true, mean_speed: Some((80, 160))))
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Scenario {
+    pub seconds: f32, // simulated, including the warmup
+    pub warmup: f32,  // simulated seconds left out of the measurements, while the road settles
+    pub cars: usize,
+    pub mix: TemperamentMix,
+    pub lawfulness: DriverLawfulness,
+    pub patience: DriverPatience,
+    pub attentiveness: DriverAttentiveness,
+    pub anticipation: bool,     // see `CarFollowingConfig`
+    pub perception_noise: bool, // see `PerceptionNoiseConfig`
+    pub seed: u64,              // shuffles the cars and seeds the simulation's own randomness
+    pub expect: Expectations,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario {
+            seconds: SCENARIO_SECONDS,
+            warmup: 0.,
+            cars: HEADLESS_CARS,
+            mix: TemperamentMix::default(),
+            lawfulness: DriverLawfulness::Orderly,
+            patience: DriverPatience::Normal,
+            attentiveness: DriverAttentiveness::Normal,
+            anticipation: false,
+            perception_noise: false,
+            seed: SIMULATION_SEED,
+            expect: Expectations::default(),
+        }
+    }
+}
+
+impl Scenario {
+    pub fn read(path: impl AsRef<Path>) -> io::Result<Scenario> {
+        let text = fs::read_to_string(path)?;
+        let scenario: Scenario = ron::from_str(&text)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+        scenario
+            .validate()
+            .map_err(|message| io::Error::new(io::ErrorKind::InvalidData, message))?;
+        Ok(scenario)
+    }
+
+    // the most cars that fit on the road with each one at least its tail distance behind the car
+    // ahead, taking the closest any temperament in the mix is willing to follow
+    pub fn capacity(&self) -> usize {
+        let tail_threshold = self
+            .mix
+            .0
+            .iter()
+            .filter(|(_, weight)| *weight > 0)
+            .map(|(temperament, _)| driver_temperament_tail_threshold(temperament))
+            .fold(f32::INFINITY, f32::min);
+        let min_tail_distance = CAR_SIZE.y * tail_threshold;
+        let per_lane = ((TOP_WALL - BOTTOM_WALL) / (CAR_SIZE.y + min_tail_distance)) as usize;
+
+        per_lane * NUM_LANES as usize
+    }
+
+    // any more cars than the road holds would start out inside each other's tail distance, or
+    // on top of each other
+    pub fn validate(&self) -> Result<(), String> {
+        if self.cars > self.capacity() {
+            return Err(format!(
+                "{} cars don't fit on the road; it holds at most {} with the mix {}",
+                self.cars,
+                self.capacity(),
+                self.mix
+            ));
+        }
+        Ok(())
+    }
+
+    // one temperament per car, in the proportions of the mix and in an order set by the seed
+    pub fn temperaments(&self) -> Vec<DriverTemperament> {
+        let mut temperaments = self.mix.split(self.cars);
+        temperaments.shuffle(&mut ChaCha8Rng::seed_from_u64(self.seed));
+        temperaments
+    }
+}
+
+// what a run of the scenario has to come out with for the regression tests to pass; see
+// `tests/scenarios.rs`. each is checked against the measurements taken after the warmup
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Expectations {
+    pub no_crashes: bool,
+    pub min_throughput: Option<f32>, // cars per minute past the end of the road
+    pub mean_speed: Option<(f32, f32)>, // lowest and highest
+    pub orderly_keep_right: Option<f32>, // longest seconds any orderly car spends out of the right lane
+}
+
+impl Expectations {
+    // one line for each expectation the run fell short of
+    pub fn failures(&self, summary: &RunSummary) -> Vec<String> {
+        let mut failures = vec![];
+
+        if self.no_crashes && summary.crashes > 0 {
+            failures.push(format!("{} crashes, expected none", summary.crashes));
+        }
+        if let Some(min) = self.min_throughput {
+            if summary.throughput < min {
+                failures.push(format!(
+                    "throughput {:.2} cars per minute, expected at least {min}",
+                    summary.throughput
+                ));
+            }
+        }
+        if let Some((low, high)) = self.mean_speed {
+            if !(low..=high).contains(&summary.mean_speed) {
+                failures.push(format!(
+                    "mean speed {:.2}, expected between {low} and {high}",
+                    summary.mean_speed
+                ));
+            }
+        }
+        if let Some(max) = self.orderly_keep_right {
+            if summary.longest_out_of_right_lane > max {
+                failures.push(format!(
+                    "an orderly car spent {:.2}s out of the right lane, expected at most {max}s",
+                    summary.longest_out_of_right_lane
+                ));
+            }
+        }
+
+        failures
+    }
+}
+
+// relative weights of each temperament on the road
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(transparent)]
+pub struct TemperamentMix(pub Vec<(DriverTemperament, u32)>);
+
+impl Default for TemperamentMix {
+    // one of each
+    fn default() -> Self {
+        TemperamentMix(
+            [
+                DriverTemperament::Psychotic,
+                DriverTemperament::Aggressive,
+                DriverTemperament::Calm,
+                DriverTemperament::Passive,
+            ]
+            .into_iter()
+            .map(|temperament| (temperament, 1))
+            .collect(),
+        )
+    }
+}
+
+impl TemperamentMix {
+    // e.g. "calm:3+aggressive:1"; a temperament without a weight counts once
+    pub fn parse(spec: &str) -> Result<TemperamentMix, String> {
+        let mut mix = vec![];
+        for part in spec.split('+') {
+            let (name, weight) = part.split_once(':').unwrap_or((part, "1"));
+            let temperament = match name.trim().to_lowercase().as_str() {
+                "psychotic" => DriverTemperament::Psychotic,
+                "aggressive" => DriverTemperament::Aggressive,
+                "calm" => DriverTemperament::Calm,
+                "passive" => DriverTemperament::Passive,
+                _ => return Err(format!("unknown temperament {name}")),
+            };
+            let weight = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("{name} needs a whole number weight, not {weight}"))?;
+            mix.push((temperament, weight));
+        }
+
+        if mix.iter().all(|(_, weight)| *weight == 0) {
+            return Err(format!("{spec} gives every temperament a weight of zero"));
+        }
+        Ok(TemperamentMix(mix))
+    }
+
+    // `cars` temperaments in proportion to the weights, rounding so the total comes out exact
+    fn split(&self, cars: usize) -> Vec<DriverTemperament> {
+        let total: u32 = self.0.iter().map(|(_, weight)| weight).sum();
+        let mut temperaments = Vec::with_capacity(cars);
+        let mut weight_so_far = 0;
+
+        for (temperament, weight) in &self.0 {
+            let start = cars * weight_so_far as usize / total.max(1) as usize;
+            weight_so_far += weight;
+            let end = cars * weight_so_far as usize / total.max(1) as usize;
+            temperaments.extend(std::iter::repeat_n(temperament.clone(), end - start));
+        }
+        temperaments
+    }
+}
+
+// the same form `parse` takes, so it can go straight into a results table
+impl fmt::Display for TemperamentMix {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<String> = self
+            .0
+            .iter()
+            .map(|(temperament, weight)| format!("{temperament:?}:{weight}").to_lowercase())
+            .collect();
+        write!(f, "{}", parts.join("+"))
+    }
+}
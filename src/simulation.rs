@@ -0,0 +1,139 @@
+use bevy::prelude::*;
+
+use crate::components::*;
+use crate::events::*;
+use crate::profiling::*;
+use crate::replay::*;
+use crate::resources::*;
+use crate::rewind::*;
+use crate::save::*;
+use crate::systems;
+use crate::util::*;
+
+// everything the simulation itself needs, with or without a window: its resources, states,
+// events and the fixed-timestep chain that advances it. drawing, input and the panels are
+// added on top of this by the windowed app
+#[derive(Default)]
+pub struct SimulationPlugin {
+    // leaves out the trails, trajectories and heatmap, which nothing without a window looks at.
+    // the rewind buffer, the decision trace and the profiler are off until the windowed app or
+    // `--profile` turns them on, so a headless run pays for none of them
+    pub headless: bool,
+}
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        let windowed = !self.headless;
+
+        app
+            ///////////////
+            // RESOURCES //
+            ///////////////
+            .init_resource::<MergeCooperationStats>()
+            .init_resource::<PerceptionConfig>()
+            .init_resource::<CarFollowingConfig>()
+            .init_resource::<PerceptionNoiseConfig>()
+            .init_resource::<SimulationRng>()
+            .init_resource::<SimulationClock>()
+            .init_resource::<TrafficMetrics>()
+            .init_resource::<Trajectories>()
+            .init_resource::<Heatmap>()
+            .init_resource::<Trails>()
+            .init_resource::<Recorder>()
+            .init_resource::<Rewind>()
+            .init_resource::<Breakpoints>()
+            .init_resource::<DecisionTrace>()
+            .init_resource::<Profiler>()
+            .init_resource::<InvariantChecks>()
+            .init_resource::<SaveSlot>()
+            ////////////
+            // STATES //
+            ////////////
+            .init_state::<PauseState>()
+            .init_state::<SimulationMode>()
+            ///////////
+            // TYPES //
+            ///////////
+            .register_type::<Car>()
+            .register_type::<LaneEntity>()
+            .register_type::<Collider>()
+            .register_type::<Velocity>()
+            .register_type::<Friction>()
+            .register_type::<SelectedEntity>()
+            .register_type::<DriverAgent>()
+            .register_type::<LaneChanger>()
+            .register_type::<Frozen>()
+            .register_type::<Acceleration>()
+            .register_type::<VehicleClass>()
+            .register_type::<ActiveLaneChange>()
+            .register_type::<TurnSignal>()
+            .register_type::<CourtesyResponse>()
+            .register_type::<Perception>()
+            .register_type::<PerceptionConfig>()
+            .register_type::<CarFollowingConfig>()
+            .register_type::<PerceptionNoiseConfig>()
+            ////////////
+            // EVENTS //
+            ////////////
+            .add_event::<SelectEntityEvent>()
+            .add_event::<DeselectEntityEvent>()
+            .add_event::<DriverStateChangeEvent>()
+            .add_event::<MergeInteractionEvent>()
+            /////////////
+            // SYSTEMS //
+            /////////////
+            .add_systems(PostStartup, load_on_startup_system)
+            .add_systems(
+                OnEnter(PauseState::Running),
+                systems::reset_breakpoints_tripped,
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    profiled(FixedUpdate, rewind_resume_system),
+                    profiled(FixedUpdate, systems::simulation_clock_system),
+                    profiled(FixedUpdate, systems::perception_error_system),
+                    profiled(FixedUpdate, systems::collision_system),
+                    profiled(FixedUpdate, systems::perception_system),
+                    profiled(FixedUpdate, systems::apply_friction),
+                    profiled(FixedUpdate, systems::apply_velocity),
+                    profiled(FixedUpdate, systems::wrap_position),
+                    profiled(FixedUpdate, systems::agent_check_lane_change_system),
+                    profiled(FixedUpdate, systems::agent_turn_signal_system),
+                    profiled(FixedUpdate, systems::agent_courtesy_system),
+                    profiled(FixedUpdate, systems::agent_active_lane_change_system),
+                    profiled(FixedUpdate, systems::agent_state_system),
+                    profiled(FixedUpdate, systems::agent_drive_system),
+                    profiled(FixedUpdate, systems::measure_acceleration_system),
+                    // everything that observes the finished tick
+                    (
+                        profiled(FixedUpdate, systems::merge_interaction_listener),
+                        profiled(FixedUpdate, systems::metrics_sample_system),
+                        (
+                            profiled(FixedUpdate, systems::trajectory_sample_system),
+                            profiled(FixedUpdate, systems::trail_sample_system),
+                            profiled(FixedUpdate, systems::heatmap_update_system),
+                        )
+                            .chain()
+                            .run_if(move || windowed),
+                        profiled(FixedUpdate, systems::breakpoint_system),
+                        profiled(FixedUpdate, record_frame_system),
+                        profiled(FixedUpdate, rewind_capture_system).run_if(rewind_enabled),
+                        profiled(FixedUpdate, systems::invariant_check_system)
+                            .run_if(systems::invariant_checks_enabled),
+                    )
+                        .chain(),
+                )
+                    .run_if(
+                        in_state(PauseState::Running)
+                            .and_then(in_state(SimulationMode::Live))
+                            .and_then(systems::breakpoints_not_tripped),
+                    )
+                    .chain(),
+            )
+            .add_systems(
+                Update,
+                profiled(Update, systems::driver_state_change_listener),
+            );
+    }
+}
@@ -0,0 +1,310 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::components::*;
+use crate::history::*;
+use crate::resources::*;
+use crate::rewind::*;
+
+// the complete state of the simulation after one tick: every car with all of its in-progress
+// maneuvers, the clock and the random number generator. restoring a snapshot puts the world
+// back exactly as it was, so the simulation carries on from there as it did the first time
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct VehicleState {
+    pub entity: Entity,
+    pub translation: Vec3,
+    pub velocity: Velocity,
+    pub acceleration: Acceleration,
+    pub lane: LaneEntity,
+    pub driver_agent: DriverAgent,
+    pub perception: Perception,
+    pub vehicle_class: VehicleClass,
+    pub frozen: bool,
+    pub active_lane_change: Option<ActiveLaneChange>,
+    pub turn_signal: Option<TurnSignal>,
+    pub courtesy_response: Option<CourtesyResponse>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SimulationSnapshot {
+    pub clock: SimulationClock,
+    pub rng: ChaCha8Rng,
+    pub merge_stats: MergeCooperationStats,
+    pub vehicles: Vec<VehicleState>,
+}
+
+impl SimulationSnapshot {
+    pub fn capture(world: &mut World) -> SimulationSnapshot {
+        let vehicles = world
+            .query_filtered::<(
+                Entity,
+                &Transform,
+                &Velocity,
+                &Acceleration,
+                &LaneEntity,
+                &DriverAgent,
+                &Perception,
+                &VehicleClass,
+                Has<Frozen>,
+                Option<&ActiveLaneChange>,
+                Option<&TurnSignal>,
+                Option<&CourtesyResponse>,
+            ), With<Car>>()
+            .iter(world)
+            .map(
+                |(
+                    entity,
+                    transform,
+                    velocity,
+                    acceleration,
+                    lane,
+                    driver_agent,
+                    perception,
+                    vehicle_class,
+                    frozen,
+                    active_lane_change,
+                    turn_signal,
+                    courtesy_response,
+                )| VehicleState {
+                    entity,
+                    translation: transform.translation,
+                    velocity: velocity.clone(),
+                    acceleration: acceleration.clone(),
+                    lane: lane.clone(),
+                    driver_agent: driver_agent.clone(),
+                    perception: perception.clone(),
+                    vehicle_class: *vehicle_class,
+                    frozen,
+                    active_lane_change: active_lane_change.cloned(),
+                    turn_signal: turn_signal.cloned(),
+                    courtesy_response: courtesy_response.cloned(),
+                },
+            )
+            .collect();
+
+        SimulationSnapshot {
+            clock: world.resource::<SimulationClock>().clone(),
+            rng: world.resource::<SimulationRng>().0.clone(),
+            merge_stats: world.resource::<MergeCooperationStats>().clone(),
+            vehicles,
+        }
+    }
+
+    pub fn restore(&self, world: &mut World) {
+        *world.resource_mut::<SimulationClock>() = self.clock.clone();
+        world.resource_mut::<SimulationRng>().0 = self.rng.clone();
+        *world.resource_mut::<MergeCooperationStats>() = self.merge_stats.clone();
+
+        // cars that weren't around yet at this tick
+        let kept: HashSet<Entity> = self.vehicles.iter().map(|vehicle| vehicle.entity).collect();
+        let current: Vec<Entity> = world
+            .query_filtered::<Entity, With<Car>>()
+            .iter(world)
+            .collect();
+
+        for entity in current {
+            if !kept.contains(&entity) {
+                world.entity_mut(entity).despawn_recursive();
+            }
+        }
+
+        // cars that are still around are updated in place, so they keep their selection and
+        // paint; cars removed since come back under fresh ids
+        let removed: Vec<Entity> = self
+            .vehicles
+            .iter()
+            .map(|vehicle| vehicle.entity)
+            .filter(|entity| world.get::<Car>(*entity).is_none())
+            .collect();
+        let entity_map = reserve_vehicle_ids(world, removed);
+
+        if entity_map.is_empty() {
+            for vehicle in &self.vehicles {
+                vehicle.restore(world);
+            }
+        } else {
+            let mut snapshot = self.clone();
+            snapshot.remap_entities(&entity_map);
+            for vehicle in &snapshot.vehicles {
+                vehicle.restore(world);
+            }
+            remap_vehicle_references(world, &entity_map);
+        }
+    }
+
+    // point every reference to a car at its new id; see `reserve_vehicle_ids`
+    pub fn remap_entities(&mut self, entity_map: &HashMap<Entity, Entity>) {
+        let remap = |entity: &mut Entity| {
+            if let Some(mapped) = entity_map.get(entity) {
+                *entity = *mapped;
+            }
+        };
+
+        for vehicle in &mut self.vehicles {
+            remap(&mut vehicle.entity);
+
+            for vehicle_ahead in &mut vehicle.driver_agent.collision_information.vehicles_ahead {
+                remap(&mut vehicle_ahead.entity);
+            }
+            for detection in &mut vehicle.perception.detections {
+                remap(&mut detection.entity);
+            }
+            if let Some(courtesy_response) = &mut vehicle.courtesy_response {
+                remap(&mut courtesy_response.merging_entity);
+            }
+        }
+    }
+}
+
+impl VehicleState {
+    fn restore(&self, world: &mut World) {
+        if world.get::<Car>(self.entity).is_none() {
+            respawn_vehicle(world, self.entity, self.translation);
+        }
+
+        let mut entity = world.entity_mut(self.entity);
+
+        if let Some(mut transform) = entity.get_mut::<Transform>() {
+            transform.translation = self.translation;
+        }
+
+        entity.insert((
+            self.velocity.clone(),
+            self.acceleration.clone(),
+            self.lane.clone(),
+            self.driver_agent.clone(),
+            self.perception.clone(),
+            self.vehicle_class,
+        ));
+
+        insert_or_remove(&mut entity, self.frozen.then_some(Frozen));
+        insert_or_remove(&mut entity, self.active_lane_change.clone());
+        insert_or_remove(&mut entity, self.turn_signal.clone());
+        insert_or_remove(&mut entity, self.courtesy_response.clone());
+    }
+}
+
+// a plain car under `entity`, an id from `reserve_vehicle_ids`; restoring fills in the rest
+pub fn respawn_vehicle(world: &mut World, entity: Entity, translation: Vec3) {
+    let mesh = world
+        .resource_mut::<Assets<Mesh>>()
+        .add(Rectangle::default())
+        .into();
+    let car_bundle = pickable_car(CarBundle::new(
+        translation,
+        mesh,
+        Handle::default(), // painted by `vehicle_color_system`
+    ));
+
+    world.entity_mut(entity).insert(car_bundle);
+}
+
+// fresh ids for cars coming back into the world, one for each id they had before. a fresh id
+// can't be one that something stale still points at, and they're handed out in the same order
+// as the old ones, so systems that visit cars in entity order visit them as they did before
+pub fn reserve_vehicle_ids(
+    world: &mut World,
+    old_entities: impl IntoIterator<Item = Entity>,
+) -> HashMap<Entity, Entity> {
+    let mut old_entities: Vec<Entity> = old_entities.into_iter().collect();
+    old_entities.sort();
+
+    let mut entities: Vec<Entity> = old_entities
+        .iter()
+        .map(|_| world.spawn_empty().id())
+        .collect();
+    entities.sort();
+
+    old_entities.into_iter().zip(entities).collect()
+}
+
+// after cars come back under new ids, point everything that still refers to them by their old
+// ones at the new: the other cars, the undo history, the rewind buffer, breakpoints and the
+// per-car plots and trace
+pub fn remap_vehicle_references(world: &mut World, entity_map: &HashMap<Entity, Entity>) {
+    if entity_map.is_empty() {
+        return;
+    }
+
+    let remap = |entity: &mut Entity| {
+        if let Some(mapped) = entity_map.get(entity) {
+            *entity = *mapped;
+        }
+    };
+
+    let mut cars = world.query_filtered::<(
+        &mut DriverAgent,
+        &mut Perception,
+        Option<&mut CourtesyResponse>,
+    ), With<Car>>();
+    for (mut agent, mut perception, courtesy_response) in cars.iter_mut(world) {
+        for vehicle_ahead in &mut agent.collision_information.vehicles_ahead {
+            remap(&mut vehicle_ahead.entity);
+        }
+        for detection in &mut perception.detections {
+            remap(&mut detection.entity);
+        }
+        if let Some(mut courtesy_response) = courtesy_response {
+            remap(&mut courtesy_response.merging_entity);
+        }
+    }
+
+    // the windowed app and a headless run keep different parts of this
+    if let Some(mut history) = world.get_resource_mut::<History>() {
+        history.remap_entities(entity_map);
+    }
+
+    if let Some(mut rewind) = world.get_resource_mut::<Rewind>() {
+        for snapshot in &mut rewind.snapshots {
+            snapshot.remap_entities(entity_map);
+        }
+    }
+
+    if let Some(mut breakpoints) = world.get_resource_mut::<Breakpoints>() {
+        for breakpoint in &mut breakpoints.breakpoints {
+            if let BreakCondition::LaneChange(Some(entity)) = &mut breakpoint.condition {
+                remap(entity);
+            }
+            breakpoint.active = breakpoint
+                .active
+                .drain()
+                .map(|entity| *entity_map.get(&entity).unwrap_or(&entity))
+                .collect();
+        }
+        for hit in &mut breakpoints.hits {
+            hit.entities.iter_mut().for_each(remap);
+        }
+    }
+
+    if let Some(mut trajectories) = world.get_resource_mut::<Trajectories>() {
+        remap_keys(&mut trajectories.vehicles, entity_map);
+    }
+    if let Some(mut trails) = world.get_resource_mut::<Trails>() {
+        remap_keys(&mut trails.vehicles, entity_map);
+    }
+    if let Some(mut decision_trace) = world.get_resource_mut::<DecisionTrace>() {
+        remap_keys(&mut decision_trace.vehicles, entity_map);
+    }
+}
+
+fn remap_keys<T>(map: &mut HashMap<Entity, T>, entity_map: &HashMap<Entity, Entity>) {
+    for (old, new) in entity_map {
+        if let Some(value) = map.remove(old) {
+            map.insert(*new, value);
+        }
+    }
+}
+
+fn insert_or_remove<T: Component>(entity: &mut EntityWorldMut, component: Option<T>) {
+    match component {
+        Some(component) => {
+            entity.insert(component);
+        }
+        None => {
+            entity.remove::<T>();
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+use bevy_mod_picking::prelude::*;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::events::*;
+use crate::resources::*;
+use crate::util::*;
+
+pub fn breakpoint_system(
+    mut breakpoints: ResMut<Breakpoints>,
+    clock: Res<SimulationClock>,
+    mut state_change_reader: EventReader<DriverStateChangeEvent>,
+    lane_change_query: Query<Entity, Added<ActiveLaneChange>>,
+    query: Query<(Entity, &DriverAgent, &Acceleration), With<Car>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut selection_query: Query<(Entity, &mut PickSelection, Has<SelectedEntity>), With<Car>>,
+    mut select_writer: EventWriter<SelectEntityEvent>,
+    mut deselect_writer: EventWriter<DeselectEntityEvent>,
+) {
+    let state_changes: Vec<&DriverStateChangeEvent> = state_change_reader.read().collect();
+
+    // the car ahead, for conditions that involve two cars
+    let with_car_ahead = |entity: Entity| {
+        let mut entities = vec![entity];
+        if let Some(vehicle_ahead) = query
+            .get(entity)
+            .ok()
+            .and_then(|(_, agent, _)| agent.collision_information.vehicles_ahead.first())
+        {
+            entities.push(vehicle_ahead.entity);
+        }
+        entities
+    };
+
+    let mut hits = vec![];
+
+    for breakpoint in &mut breakpoints.breakpoints {
+        if !breakpoint.enabled {
+            continue;
+        }
+
+        let mut involved: Vec<Vec<Entity>> = vec![];
+
+        match &breakpoint.condition {
+            BreakCondition::Collision => {
+                for change in &state_changes {
+                    if change.to == DriverState::Crashed {
+                        involved.push(with_car_ahead(change.entity));
+                    }
+                }
+            }
+            BreakCondition::EntersState(state) => {
+                for change in &state_changes {
+                    if change.to == *state {
+                        involved.push(vec![change.entity]);
+                    }
+                }
+            }
+            BreakCondition::LaneChange(target) => {
+                for entity in &lane_change_query {
+                    if target.is_none_or(|target| target == entity) {
+                        involved.push(vec![entity]);
+                    }
+                }
+            }
+            BreakCondition::GapBelow(_) | BreakCondition::DecelerationAbove(_) => {
+                for (entity, agent, acceleration) in &query {
+                    let is_met = match breakpoint.condition {
+                        BreakCondition::GapBelow(gap) => {
+                            let front_distance = agent.collision_information.front_distance;
+                            front_distance >= 0. && front_distance < gap
+                        }
+                        BreakCondition::DecelerationAbove(deceleration) => {
+                            -acceleration.value > deceleration
+                        }
+                        _ => unreachable!(),
+                    };
+
+                    if !is_met {
+                        breakpoint.active.remove(&entity);
+                    } else if breakpoint.active.insert(entity) {
+                        involved.push(with_car_ahead(entity));
+                    }
+                }
+            }
+        }
+
+        for entities in involved {
+            hits.push(BreakpointHit {
+                time: clock.elapsed,
+                condition: breakpoint.condition.clone(),
+                entities,
+            });
+        }
+    }
+
+    if hits.is_empty() {
+        return;
+    }
+
+    let involved: Vec<Entity> = hits.iter().flat_map(|hit| hit.entities.clone()).collect();
+    for hit in &hits {
+        info!(
+            "breakpoint hit at {:.2}s: {} {:?}",
+            hit.time,
+            hit.condition.describe(),
+            hit.entities
+        );
+    }
+
+    breakpoints.hits.extend(hits);
+    while breakpoints.hits.len() > BREAKPOINT_HITS_LENGTH {
+        breakpoints.hits.pop_front();
+    }
+
+    breakpoints.tripped = true;
+    next_pause_state.set(PauseState::Paused);
+
+    select_only(
+        &involved,
+        &mut selection_query,
+        &mut select_writer,
+        &mut deselect_writer,
+    );
+}
+
+pub fn breakpoints_not_tripped(breakpoints: Res<Breakpoints>) -> bool {
+    !breakpoints.tripped
+}
+
+pub fn reset_breakpoints_tripped(mut breakpoints: ResMut<Breakpoints>) {
+    breakpoints.tripped = false;
+}
+
+// make `entities` the whole selection, as clicking them on the road would
+pub fn select_only(
+    entities: &[Entity],
+    selection_query: &mut Query<(Entity, &mut PickSelection, Has<SelectedEntity>), With<Car>>,
+    select_writer: &mut EventWriter<SelectEntityEvent>,
+    deselect_writer: &mut EventWriter<DeselectEntityEvent>,
+) {
+    for (entity, mut selection, is_selected) in selection_query {
+        if entities.contains(&entity) {
+            selection.is_selected = true;
+            if !is_selected {
+                select_writer.send(SelectEntityEvent(entity));
+            }
+        } else if is_selected {
+            selection.is_selected = false;
+            deselect_writer.send(DeselectEntityEvent(entity));
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+    window::PrimaryWindow,
+};
+use bevy_picking_egui::bevy_egui::EguiContexts;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::resources::*;
+use crate::util::*;
+
+pub fn camera_zoom_system(
+    mut wheel_reader: EventReader<MouseWheel>,
+    mut egui_contexts: EguiContexts,
+    cursor_coords: Res<CursorWorldCoords>,
+    camera_mode: Res<State<CameraMode>>,
+    mut next_camera_mode: ResMut<NextState<CameraMode>>,
+    mut query: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let notches: f32 = wheel_reader
+        .read()
+        .map(|wheel| match wheel.unit {
+            MouseScrollUnit::Line => wheel.y,
+            MouseScrollUnit::Pixel => wheel.y / 100.,
+        })
+        .sum();
+
+    if notches == 0. || egui_contexts.ctx_mut().wants_pointer_input() {
+        return;
+    }
+
+    let (mut transform, mut projection) = query.single_mut();
+
+    let old_scale = projection.scale;
+    projection.scale = (old_scale * (1. - CAMERA_ZOOM_STEP).powf(notches))
+        .clamp(CAMERA_MIN_SCALE, CAMERA_MAX_SCALE);
+
+    // a followed car stays centered; otherwise zoom around the cursor, keeping the point under it
+    // fixed on screen
+    if *camera_mode.get() == CameraMode::FollowSelected {
+        return;
+    }
+
+    let cursor = cursor_coords.0.extend(transform.translation.z);
+    transform.translation =
+        cursor + (transform.translation - cursor) * projection.scale / old_scale;
+
+    next_camera_mode.set(CameraMode::Free);
+}
+
+pub fn camera_pan_system(
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    mut egui_contexts: EguiContexts,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut last_cursor_position: Local<Option<Vec2>>,
+    mut next_camera_mode: ResMut<NextState<CameraMode>>,
+    mut query: Query<(&mut Transform, &OrthographicProjection), With<MainCamera>>,
+) {
+    // drag with the right or middle mouse button; left is for selecting
+    let pan_buttons = [MouseButton::Right, MouseButton::Middle];
+
+    if mouse_button_input.any_just_pressed(pan_buttons)
+        && !egui_contexts.ctx_mut().wants_pointer_input()
+    {
+        *last_cursor_position = q_window.single().cursor_position();
+    }
+
+    if !mouse_button_input.any_pressed(pan_buttons) {
+        *last_cursor_position = None;
+        return;
+    }
+
+    let (Some(last), Some(current)) = (*last_cursor_position, q_window.single().cursor_position())
+    else {
+        return;
+    };
+
+    *last_cursor_position = Some(current);
+
+    let delta = current - last;
+    if delta == Vec2::ZERO {
+        return;
+    }
+
+    // window y grows downward, world y upward
+    let (mut transform, projection) = query.single_mut();
+    transform.translation.x -= delta.x * projection.scale;
+    transform.translation.y += delta.y * projection.scale;
+
+    next_camera_mode.set(CameraMode::Free);
+}
+
+pub fn camera_follow_system(
+    selected_query: Query<
+        (Entity, &Transform),
+        (With<SelectedEntity>, With<Car>, Without<MainCamera>),
+    >,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+) {
+    // with several cars selected, follows the same one the inspector shows
+    let Some((_, selected_transform)) = selected_query.iter().min_by_key(|(entity, _)| *entity)
+    else {
+        return;
+    };
+
+    let mut camera_transform = camera_query.single_mut();
+    camera_transform.translation.x = selected_transform.translation.x;
+    camera_transform.translation.y = selected_transform.translation.y;
+}
+
+pub fn camera_fit_system(
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut query: Query<(&mut Transform, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    // runs every frame while fitting, so the road stays in view as the window is resized
+    let window = q_window.single();
+    if window.width() <= 0. || window.height() <= 0. {
+        return;
+    }
+
+    let road_min = Vec2::new(LEFT_WALL, BOTTOM_WALL) - (WALL_THICKNESS / 2. + CAMERA_FIT_MARGIN);
+    let road_max = Vec2::new(RIGHT_WALL, TOP_WALL) + (WALL_THICKNESS / 2. + CAMERA_FIT_MARGIN);
+    let road_size = road_max - road_min;
+
+    let (mut transform, mut projection) = query.single_mut();
+
+    projection.scale = f32::max(road_size.x / window.width(), road_size.y / window.height());
+
+    let center = (road_min + road_max) / 2.;
+    transform.translation.x = center.x;
+    transform.translation.y = center.y;
+}
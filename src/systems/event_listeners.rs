@@ -1 +1,281 @@
+use bevy::{prelude::*, sprite::Mesh2dHandle};
 
+use crate::components::*;
+use crate::constants::*;
+use crate::events::*;
+use crate::history::*;
+use crate::replay::*;
+use crate::resources::*;
+use crate::rewind::*;
+use crate::save::*;
+use crate::util::*;
+
+pub fn driver_state_change_listener(mut reader: EventReader<DriverStateChangeEvent>) {
+    for event in reader.read() {
+        debug!(
+            "{:?} changed state {:?} -> {:?}",
+            event.entity, event.from, event.to
+        );
+    }
+}
+
+pub fn merge_interaction_listener(
+    mut reader: EventReader<MergeInteractionEvent>,
+    mut stats: ResMut<MergeCooperationStats>,
+) {
+    for event in reader.read() {
+        match &event.interaction {
+            MergeInteraction::Signaled => stats.signals += 1,
+            MergeInteraction::Responded(MergeResponse::OpenGap) => stats.gaps_opened += 1,
+            MergeInteraction::Responded(MergeResponse::Ignore) => stats.gaps_ignored += 1,
+            MergeInteraction::Responded(MergeResponse::CloseGap) => stats.gaps_closed += 1,
+            MergeInteraction::Merged => stats.merges += 1,
+            MergeInteraction::Abandoned => stats.abandoned += 1,
+        }
+
+        debug!(
+            "merge {:?}: merging={:?} responder={:?} totals={:?}",
+            event.interaction, event.merging_entity, event.responder, *stats
+        );
+    }
+}
+
+pub fn modify_component_listener(
+    mut reader: EventReader<ModifyComponentEvent>,
+    mut commands: Commands,
+) {
+    // applying needs the type registry and the entity at once, so it's deferred to a command
+    for event in reader.read() {
+        let entity = event.entity;
+        let component = event.component.clone_value();
+
+        commands.add(move |world: &mut World| {
+            let Some(type_info) = component.get_represented_type_info() else {
+                return;
+            };
+
+            let type_registry = world.resource::<AppTypeRegistry>().clone();
+            let type_registry = type_registry.read();
+
+            let Some(reflect_component) = type_registry
+                .get(type_info.type_id())
+                .and_then(|registration| registration.data::<ReflectComponent>())
+            else {
+                warn!("{} is not a reflected component", type_info.type_path());
+                return;
+            };
+
+            let Some(mut entity) = world.get_entity_mut(entity) else {
+                return;
+            };
+
+            debug!("modifying {} on {:?}", type_info.type_path(), entity.id());
+            let before = reflect_component
+                .reflect(EntityRef::from(&entity))
+                .map(|before| before.clone_value());
+            reflect_component.apply(&mut entity, component.as_ref());
+
+            let label = format!(
+                "Edit {} on {:?}",
+                type_info.type_path_table().short_path(),
+                entity.id()
+            );
+            let change = ComponentChange {
+                entity: entity.id(),
+                before,
+                after: Some(component),
+            };
+
+            drop(type_registry);
+            world
+                .resource_mut::<History>()
+                .record_or_merge(Edit::new(label, EditAction::Components(vec![change])));
+        });
+    }
+}
+
+pub fn selection_action_listener(
+    mut reader: EventReader<SelectionActionEvent>,
+    mut commands: Commands,
+    mut query: Query<
+        (
+            Entity,
+            &Transform,
+            &mut Velocity,
+            &LaneEntity,
+            &DriverAgent,
+            &Mesh2dHandle,
+            &Handle<ColorMaterial>,
+            &VehicleClass,
+            Option<&Frozen>,
+        ),
+        (With<Car>, With<SelectedEntity>),
+    >,
+    mut history: ResMut<History>,
+) {
+    for event in reader.read() {
+        info!("Applying {:?} to selected cars", event.0);
+
+        let mut records = vec![];
+        let mut changes = vec![];
+        let mut count = 0;
+
+        for (entity, transform, mut velocity, lane, agent, mesh, material, vehicle_class, frozen) in
+            &mut query
+        {
+            match event.0 {
+                SelectionAction::Delete => {
+                    count += 1;
+                    records.push(CarRecord {
+                        entity,
+                        snapshot: Some(CarSnapshot {
+                            transform: *transform,
+                            velocity: velocity.clone(),
+                            lane: lane.clone(),
+                            driver_agent: agent.clone(),
+                            mesh: mesh.clone(),
+                            material: material.clone(),
+                            vehicle_class: *vehicle_class,
+                            frozen: frozen.is_some(),
+                        }),
+                    });
+                    commands.entity(entity).despawn_recursive();
+                }
+                SelectionAction::Duplicate => {
+                    count += 1;
+
+                    // the copy starts a couple of car lengths behind, with the same driver but
+                    // none of the original's in-progress state
+                    let position = transform.translation - Vec3::new(0., CAR_SIZE.y * 2., 0.);
+
+                    let mut car_bundle = CarBundle::new(position, mesh.clone(), material.clone());
+                    car_bundle.velocity = Velocity(Vec2::new(0., velocity.y));
+                    car_bundle.acceleration.last_speed = velocity.y;
+                    car_bundle.vehicle_class = *vehicle_class;
+                    car_bundle.driver_agent = car_bundle
+                        .driver_agent
+                        .with_lawfulness(agent.lawfulness.clone())
+                        .with_temperament(agent.temperament.clone())
+                        .with_patience(agent.patience.clone())
+                        .with_attentiveness(agent.attentiveness.clone())
+                        .with_judgment(agent.judgment.clone());
+
+                    records.push(CarRecord::spawned(spawn_car(&mut commands, car_bundle)));
+                }
+                SelectionAction::Freeze if frozen.is_none() => {
+                    count += 1;
+                    changes.push(ComponentChange {
+                        entity,
+                        before: Some(velocity.clone_value()),
+                        after: Some(Velocity(Vec2::ZERO).clone_value()),
+                    });
+                    changes.push(ComponentChange {
+                        entity,
+                        before: None,
+                        after: Some(Frozen.clone_value()),
+                    });
+
+                    velocity.0 = Vec2::ZERO;
+                    commands.entity(entity).insert(Frozen);
+                }
+                SelectionAction::Unfreeze if frozen.is_some() => {
+                    count += 1;
+                    changes.push(ComponentChange {
+                        entity,
+                        before: Some(Frozen.clone_value()),
+                        after: None,
+                    });
+
+                    commands.entity(entity).remove::<Frozen>();
+                }
+                SelectionAction::Freeze | SelectionAction::Unfreeze => {}
+            }
+        }
+
+        if count == 0 {
+            continue;
+        }
+
+        let label = format!("{:?} {} cars", event.0, count);
+        let action = match event.0 {
+            SelectionAction::Delete => EditAction::Despawn(records),
+            SelectionAction::Duplicate => EditAction::Spawn(records),
+            SelectionAction::Freeze | SelectionAction::Unfreeze => EditAction::Components(changes),
+        };
+        history.record(Edit::new(label, action));
+    }
+}
+
+pub fn history_request_listener(world: &mut World) {
+    // exclusive, since undoing an edit can touch any component or resource
+    let requests: Vec<HistoryRequest> = world
+        .resource_mut::<Events<HistoryRequestEvent>>()
+        .drain()
+        .map(|event| event.0)
+        .collect();
+
+    for request in requests {
+        match request {
+            HistoryRequest::Undo => {
+                undo(world);
+            }
+            HistoryRequest::Redo => {
+                redo(world);
+            }
+            HistoryRequest::JumpTo(undo_len) => jump_to(world, undo_len),
+        }
+    }
+}
+
+pub fn replay_request_listener(world: &mut World) {
+    // exclusive, since opening and closing a replay swaps out every car
+    let requests: Vec<ReplayRequest> = world
+        .resource_mut::<Events<ReplayRequestEvent>>()
+        .drain()
+        .map(|event| event.0)
+        .collect();
+
+    for request in requests {
+        match request {
+            ReplayRequest::StartRecording => start_recording(world),
+            ReplayRequest::StopRecording => stop_recording(world),
+            ReplayRequest::Open => open_replay(world),
+            ReplayRequest::Close => close_replay(world),
+        }
+    }
+}
+
+pub fn rewind_request_listener(world: &mut World) {
+    // exclusive, since restoring a snapshot rewrites every car
+    let requests: Vec<RewindRequest> = world
+        .resource_mut::<Events<RewindRequestEvent>>()
+        .drain()
+        .map(|event| event.0)
+        .collect();
+
+    for request in requests {
+        match request {
+            RewindRequest::StepBack => step_back(world),
+            RewindRequest::StepForward => step_forward(world),
+            RewindRequest::JumpTo(position) => rewind_to(world, position),
+        }
+    }
+}
+
+pub fn save_request_listener(world: &mut World) {
+    // exclusive, since loading replaces every car and most resources
+    let requests: Vec<SaveRequest> = world
+        .resource_mut::<Events<SaveRequestEvent>>()
+        .drain()
+        .map(|event| event.0)
+        .collect();
+
+    let path = world.resource::<SaveSlot>().path.clone();
+
+    for request in requests {
+        match request {
+            SaveRequest::Save => save_state(world, &path),
+            SaveRequest::Load => load_state(world, &path),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::resources::*;
+use crate::util::*;
+
+pub fn spawn_heatmap_cells(mut commands: Commands, heatmap: Res<Heatmap>) {
+    for (lane, segments) in heatmap.cells.iter().enumerate() {
+        let lane_x = lane_idx_to_center(lane as i32).x;
+
+        for segment in 0..segments.len() {
+            let cell_y = BOTTOM_WALL + (segment as f32 + 0.5) * HEATMAP_CELL_LENGTH;
+
+            // below the lane stripes and cars
+            commands.spawn((
+                SpriteBundle {
+                    transform: Transform {
+                        translation: Vec3::new(lane_x, cell_y, -1.),
+                        scale: Vec3::new(LANE_WIDTH, HEATMAP_CELL_LENGTH, 1.),
+                        ..default()
+                    },
+                    sprite: Sprite {
+                        color: Color::NONE,
+                        ..default()
+                    },
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                HeatmapCell {
+                    lane: lane as i32,
+                    segment,
+                },
+            ));
+        }
+    }
+}
+
+pub fn heatmap_update_system(
+    mut heatmap: ResMut<Heatmap>,
+    query: Query<(&Transform, &Velocity, &LaneEntity), With<Car>>,
+    time: Res<Time>,
+) {
+    // each tick's readings are blended in with weight `1 - e^(-dt / window)`, so a reading
+    // `smoothing_window` seconds old has faded to about a third of its weight
+    let mut counts = heatmap
+        .cells
+        .iter()
+        .map(|segments| vec![(0, 0.); segments.len()])
+        .collect::<Vec<_>>();
+
+    for (transform, velocity, lane) in &query {
+        let segment = ((transform.translation.y - BOTTOM_WALL) / HEATMAP_CELL_LENGTH) as usize;
+        if let Some((count, speed_sum)) = counts
+            .get_mut(lane.0 as usize)
+            .and_then(|segments| segments.get_mut(segment))
+        {
+            *count += 1;
+            *speed_sum += velocity.y;
+        }
+    }
+
+    let weight = 1. - (-time.delta_seconds() / heatmap.smoothing_window.max(f32::EPSILON)).exp();
+
+    for (segments, segment_counts) in heatmap.cells.iter_mut().zip(counts) {
+        for (stats, (count, speed_sum)) in segments.iter_mut().zip(segment_counts) {
+            let density = count as f32 / HEATMAP_CELL_LENGTH * 1000.;
+            let flow = speed_sum / HEATMAP_CELL_LENGTH * 60.;
+
+            stats.density += (density - stats.density) * weight;
+            stats.flow += (flow - stats.flow) * weight;
+
+            if count > 0 {
+                let speed = speed_sum / count as f32;
+                if stats.seen {
+                    stats.speed += (speed - stats.speed) * weight;
+                } else {
+                    stats.speed = speed;
+                    stats.seen = true;
+                }
+            }
+        }
+    }
+}
+
+pub fn heatmap_draw_system(
+    heatmap: Res<Heatmap>,
+    mut query: Query<(&HeatmapCell, &mut Sprite, &mut Visibility)>,
+) {
+    for (cell, mut sprite, mut visibility) in &mut query {
+        *visibility = if heatmap.visible {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+
+        if !heatmap.visible {
+            continue;
+        }
+
+        let Some(stats) = heatmap
+            .cells
+            .get(cell.lane as usize)
+            .and_then(|segments| segments.get(cell.segment))
+        else {
+            continue;
+        };
+
+        sprite.color = heatmap_color(heatmap.metric, stats);
+    }
+}
+
+fn heatmap_color(metric: HeatmapMetric, stats: &HeatmapStats) -> Color {
+    match metric {
+        // red where traffic crawls, green at the speed limit; cells no car has reached are clear
+        HeatmapMetric::Speed if stats.seen => {
+            let pct = (stats.speed / SPEED_LIMIT).clamp(0., 1.);
+            Color::rgba(
+                f32::min(1., 2. * (1. - pct)),
+                f32::min(1., 2. * pct),
+                0.,
+                HEATMAP_ALPHA,
+            )
+        }
+        HeatmapMetric::Speed => Color::NONE,
+        HeatmapMetric::Density => {
+            let pct = (stats.density / HEATMAP_MAX_DENSITY).clamp(0., 1.);
+            Color::rgba(1., 0., 0., pct * HEATMAP_ALPHA)
+        }
+        HeatmapMetric::Flow => {
+            let pct = (stats.flow / HEATMAP_MAX_FLOW).clamp(0., 1.);
+            Color::rgba(0., 0.6, 1., pct * HEATMAP_ALPHA)
+        }
+    }
+}
@@ -1,8 +1,13 @@
 use bevy::{prelude::*, window::*};
+use bevy_mod_picking::prelude::*;
+use bevy_picking_egui::bevy_egui::EguiContexts;
 
 use crate::components::*;
 use crate::constants::*;
 use crate::events::*;
+use crate::history::*;
+use crate::profiling::*;
+use crate::replay::*;
 use crate::resources::*;
 use crate::util::*;
 
@@ -49,71 +54,303 @@ pub fn check_pause_input(
     }
 }
 
+pub fn check_speed_input(
+    keyboard_input: &Res<ButtonInput<KeyCode>>,
+    speed: &mut ResMut<SimulationSpeed>,
+) {
+    if keyboard_input.just_pressed(KeyCode::BracketRight) {
+        speed.faster();
+        info!("Simulation speed {}", speed.describe());
+    }
+
+    if keyboard_input.just_pressed(KeyCode::BracketLeft) {
+        speed.slower();
+        info!("Simulation speed {}", speed.describe());
+    }
+}
+
+pub fn check_anticipation_input(
+    keyboard_input: &Res<ButtonInput<KeyCode>>,
+    car_following_config: &mut ResMut<CarFollowingConfig>,
+    history: &mut ResMut<History>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyA) {
+        let before = car_following_config.clone_value();
+        car_following_config.anticipation = !car_following_config.anticipation;
+        info!("Anticipation {}", car_following_config.anticipation);
+
+        history.record(Edit::new(
+            format!("Anticipation {}", car_following_config.anticipation),
+            EditAction::Resource(ResourceChange {
+                before,
+                after: car_following_config.clone_value(),
+            }),
+        ));
+    }
+}
+
+pub fn check_perception_noise_input(
+    keyboard_input: &Res<ButtonInput<KeyCode>>,
+    noise_config: &mut ResMut<PerceptionNoiseConfig>,
+    history: &mut ResMut<History>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyN) {
+        let before = noise_config.clone_value();
+        noise_config.enabled = !noise_config.enabled;
+        info!("Perception noise {}", noise_config.enabled);
+
+        history.record(Edit::new(
+            format!("Perception noise {}", noise_config.enabled),
+            EditAction::Resource(ResourceChange {
+                before,
+                after: noise_config.clone_value(),
+            }),
+        ));
+    }
+}
+
+pub fn check_camera_input(
+    keyboard_input: &Res<ButtonInput<KeyCode>>,
+    camera_mode: &Res<State<CameraMode>>,
+    next_camera_mode: &mut ResMut<NextState<CameraMode>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyF) {
+        if *camera_mode.get() == CameraMode::FollowSelected {
+            next_camera_mode.set(CameraMode::Free);
+        } else {
+            next_camera_mode.set(CameraMode::FollowSelected);
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::KeyH) {
+        next_camera_mode.set(CameraMode::FitToRoad);
+    }
+}
+
+pub fn check_plots_input(
+    keyboard_input: &Res<ButtonInput<KeyCode>>,
+    plots_config: &mut ResMut<PlotsConfig>,
+    trajectory_view_config: &mut ResMut<TrajectoryViewConfig>,
+    heatmap: &mut ResMut<Heatmap>,
+    profiler: &mut ResMut<Profiler>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyG) {
+        plots_config.visible = !plots_config.visible;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::KeyT) {
+        trajectory_view_config.visible = !trajectory_view_config.visible;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::KeyO) {
+        heatmap.visible = !heatmap.visible;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        profiler.visible = !profiler.visible;
+        profiler.set_enabled(profiler.visible);
+    }
+}
+
+pub fn check_history_input(
+    keyboard_input: &Res<ButtonInput<KeyCode>>,
+    history_writer: &mut EventWriter<HistoryRequestEvent>,
+) {
+    if !keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+
+    let is_shift_pressed = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    // ctrl+shift+z also redoes, for those used to it
+    if keyboard_input.just_pressed(KeyCode::KeyY)
+        || (is_shift_pressed && keyboard_input.just_pressed(KeyCode::KeyZ))
+    {
+        history_writer.send(HistoryRequestEvent(HistoryRequest::Redo));
+    } else if keyboard_input.just_pressed(KeyCode::KeyZ) {
+        history_writer.send(HistoryRequestEvent(HistoryRequest::Undo));
+    }
+}
+
+// keys typed into a text field or slider are meant for it, not for the shortcuts
+fn typing_into_egui(egui_contexts: &mut EguiContexts) -> bool {
+    egui_contexts.ctx_mut().wants_keyboard_input()
+}
+
 pub fn digit_input_system(
     mut commands: Commands,
     keyboard_input: Res<ButtonInput<KeyCode>>,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut history: ResMut<History>,
+    mut egui_contexts: EguiContexts,
 ) {
+    if typing_into_egui(&mut egui_contexts) {
+        return;
+    }
+
     if keyboard_input.any_just_pressed(DIGIT_KEYS) {
         for key in keyboard_input.get_just_pressed() {
             if DIGIT_KEYS.contains(key) {
-                spawn_car_at_lane(
+                let lane_idx = digit_key_to_number(key);
+                let entity = spawn_car_at_lane(
                     digit_key_to_number(key),
                     &mut commands,
                     meshes.add(Rectangle::default()).into(),
-                    materials.add(ColorMaterial::from(Color::PURPLE)),
+                    Handle::default(), // painted by `vehicle_color_system`
                     DriverLawfulness::Orderly,
                     DriverTemperament::Calm,
                     DriverPatience::Normal,
+                    DriverAttentiveness::Normal,
                 );
+
+                history.record(Edit::new(
+                    format!("Spawn car in lane {}", lane_idx),
+                    EditAction::Spawn(vec![CarRecord::spawned(entity)]),
+                ));
             }
         }
     }
 }
 
+// keys that change the simulation; only run in the live simulation, since a change to the
+// recorded cars in a replay would be lost
 pub fn keyboard_input_system(
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut query: Query<(&mut Velocity, &Handle<ColorMaterial>), With<Car>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    debug_state: Res<State<DebugState>>,
-    mut next_debug_state: ResMut<NextState<DebugState>>,
-    pause_state: Res<State<PauseState>>,
-    mut next_pause_state: ResMut<NextState<PauseState>>,
+    mut query: Query<&mut Velocity, With<Car>>,
+    mut car_following_config: ResMut<CarFollowingConfig>,
+    mut noise_config: ResMut<PerceptionNoiseConfig>,
+    mut history: ResMut<History>,
+    mut history_writer: EventWriter<HistoryRequestEvent>,
+    mut egui_contexts: EguiContexts,
 ) {
-    check_debug_input(&keyboard_input, &debug_state, &mut next_debug_state);
-    check_pause_input(&keyboard_input, &pause_state, &mut next_pause_state);
+    if typing_into_egui(&mut egui_contexts) {
+        return;
+    }
 
-    for (mut velocity, material_handle) in &mut query {
-        let mut new_color_o: Option<Color> = None;
+    check_anticipation_input(&keyboard_input, &mut car_following_config, &mut history);
+    check_perception_noise_input(&keyboard_input, &mut noise_config, &mut history);
+    check_history_input(&keyboard_input, &mut history_writer);
 
+    for mut velocity in &mut query {
         if keyboard_input.pressed(KeyCode::ArrowUp) {
             debug!("VROOM {}", velocity.y);
             velocity.y += CAR_GAS_POWER;
-
-            new_color_o = Some(Color::GREEN);
         } else if keyboard_input.pressed(KeyCode::ArrowDown) {
             debug!("SKRRR {}", velocity.y);
             velocity.y -= CAR_BRAKE_POWER;
             velocity.y = f32::max(velocity.y, 0.0);
-
-            new_color_o = Some(Color::RED);
         }
+    }
+}
 
-        if let Some(material) = materials.get_mut(material_handle) {
-            if let Some(color) = new_color_o {
-                material.color = color;
-            }
-        }
+// keys that only change what is shown, or whether and how fast it runs; these work in a replay too
+pub fn view_input_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    debug_state: Res<State<DebugState>>,
+    mut next_debug_state: ResMut<NextState<DebugState>>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    camera_mode: Res<State<CameraMode>>,
+    mut next_camera_mode: ResMut<NextState<CameraMode>>,
+    mut plots_config: ResMut<PlotsConfig>,
+    mut trajectory_view_config: ResMut<TrajectoryViewConfig>,
+    mut heatmap: ResMut<Heatmap>,
+    mut profiler: ResMut<Profiler>,
+    mut speed: ResMut<SimulationSpeed>,
+    mut egui_contexts: EguiContexts,
+) {
+    if typing_into_egui(&mut egui_contexts) {
+        return;
+    }
+
+    check_debug_input(&keyboard_input, &debug_state, &mut next_debug_state);
+    check_pause_input(&keyboard_input, &pause_state, &mut next_pause_state);
+    check_speed_input(&keyboard_input, &mut speed);
+    check_camera_input(&keyboard_input, &camera_mode, &mut next_camera_mode);
+    check_plots_input(
+        &keyboard_input,
+        &mut plots_config,
+        &mut trajectory_view_config,
+        &mut heatmap,
+        &mut profiler,
+    );
+}
+
+// while paused, Left / Right step one tick back / forward through the rewind buffer
+pub fn rewind_input_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut rewind_writer: EventWriter<RewindRequestEvent>,
+    mut egui_contexts: EguiContexts,
+) {
+    if typing_into_egui(&mut egui_contexts) {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::ArrowLeft) {
+        rewind_writer.send(RewindRequestEvent(RewindRequest::StepBack));
+    }
+
+    if keyboard_input.just_pressed(KeyCode::ArrowRight) {
+        rewind_writer.send(RewindRequestEvent(RewindRequest::StepForward));
+    }
+}
+
+// F5 saves the simulation, F9 loads it back
+pub fn save_input_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut save_writer: EventWriter<SaveRequestEvent>,
+    mut egui_contexts: EguiContexts,
+) {
+    if typing_into_egui(&mut egui_contexts) {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        save_writer.send(SaveRequestEvent(SaveRequest::Save));
+    }
+
+    if keyboard_input.just_pressed(KeyCode::F9) {
+        save_writer.send(SaveRequestEvent(SaveRequest::Load));
     }
 }
 
+// R starts / stops recording, Shift+R opens / closes the last recording
+pub fn replay_input_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    simulation_mode: Res<State<SimulationMode>>,
+    recorder: Res<Recorder>,
+    mut replay_writer: EventWriter<ReplayRequestEvent>,
+    mut egui_contexts: EguiContexts,
+) {
+    if typing_into_egui(&mut egui_contexts) {
+        return;
+    }
+
+    if !keyboard_input.just_pressed(KeyCode::KeyR) {
+        return;
+    }
+
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    let request = match (shift, simulation_mode.get()) {
+        (true, SimulationMode::Live) => ReplayRequest::Open,
+        (true, SimulationMode::Replay) => ReplayRequest::Close,
+        (false, SimulationMode::Live) if recorder.recording.is_some() => {
+            ReplayRequest::StopRecording
+        }
+        (false, SimulationMode::Live) => ReplayRequest::StartRecording,
+        (false, SimulationMode::Replay) => return,
+    };
+
+    replay_writer.send(ReplayRequestEvent(request));
+}
+
 pub fn mouse_click_system(
     mouse_button_input: Res<ButtonInput<MouseButton>>,
     q_windows: Query<&Window, With<PrimaryWindow>>,
+    cursor_coords: Res<CursorWorldCoords>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
     if mouse_button_input.pressed(MouseButton::Left) {
         // info!("left mouse currently pressed");
@@ -121,19 +358,18 @@ pub fn mouse_click_system(
 
     if mouse_button_input.just_pressed(MouseButton::Left) {
         // info!("left mouse just pressed");
-        if let Some(position) = q_windows.single().cursor_position() {
-            let adjusted_pos = cursor_pos_to_screen_space(&position);
-
-            let lane_idx = lane_idx_from_screen_pos(&Vec2::from((adjusted_pos.x, adjusted_pos.y)));
+        if q_windows.single().cursor_position().is_some() {
+            let lane_idx = lane_idx_from_screen_pos(&cursor_coords.0);
 
             spawn_car_at_lane(
                 lane_idx,
                 &mut commands,
                 meshes.add(Rectangle::default()).into(),
-                materials.add(ColorMaterial::from(Color::PURPLE)),
+                Handle::default(),
                 DriverLawfulness::Orderly,
                 DriverTemperament::Calm,
                 DriverPatience::Normal,
+                DriverAttentiveness::Normal,
             );
         }
     }
@@ -143,6 +379,50 @@ pub fn mouse_click_system(
     }
 }
 
+pub fn box_select_system(
+    mouse_button_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    cursor_coords: Res<CursorWorldCoords>,
+    mut egui_contexts: EguiContexts,
+    mut drag_start: Local<Option<Vec2>>,
+    mut query: Query<(Entity, &Transform, &mut PickSelection), With<Car>>,
+    mut select_writer: EventWriter<SelectEntityEvent>,
+    mut gizmos: Gizmos,
+) {
+    // shift-drag adds every car inside the dragged box to the selection; shift-click on a single
+    // car is handled by the picking plugin
+    if mouse_button_input.just_pressed(MouseButton::Left)
+        && keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+        && !egui_contexts.ctx_mut().wants_pointer_input()
+    {
+        *drag_start = Some(cursor_coords.0);
+    }
+
+    let Some(start) = *drag_start else {
+        return;
+    };
+
+    let min = start.min(cursor_coords.0);
+    let max = start.max(cursor_coords.0);
+
+    if mouse_button_input.pressed(MouseButton::Left) {
+        gizmos.rect_2d((min + max) / 2., 0., max - min, Color::WHITE);
+        return;
+    }
+
+    *drag_start = None;
+
+    for (entity, transform, mut selection) in &mut query {
+        let position = transform.translation.truncate();
+        if selection.is_selected || position.cmplt(min).any() || position.cmpgt(max).any() {
+            continue;
+        }
+
+        selection.is_selected = true;
+        select_writer.send(SelectEntityEvent(entity));
+    }
+}
+
 fn digit_key_to_number(key: &KeyCode) -> i32 {
     match key {
         KeyCode::Digit1 => 1,
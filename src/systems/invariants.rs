@@ -0,0 +1,156 @@
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::resources::*;
+use crate::util::*;
+
+pub fn invariant_checks_enabled(checks: Res<InvariantChecks>) -> bool {
+    checks.enabled
+}
+
+pub fn invariant_check_system(
+    mut checks: ResMut<InvariantChecks>,
+    clock: Res<SimulationClock>,
+    query: Query<
+        (
+            Entity,
+            &Transform,
+            &Velocity,
+            &LaneEntity,
+            &DriverAgent,
+            Option<&ActiveLaneChange>,
+        ),
+        With<Car>,
+    >,
+) {
+    let mut found: Vec<(Invariant, Vec<Entity>, String)> = vec![];
+
+    for (entity, transform, velocity, lane, _, active_lane_change) in &query {
+        let position = transform.translation.truncate();
+
+        if !position.is_finite() || !velocity.is_finite() {
+            found.push((
+                Invariant::NonFinite,
+                vec![entity],
+                format!("position {position}, velocity {}", velocity.0),
+            ));
+            // nothing else about this car means anything
+            continue;
+        }
+
+        if velocity.y < 0. || velocity.y > INVARIANT_MAX_SPEED || velocity.x.abs() > CAR_GAS_POWER {
+            found.push((
+                Invariant::SpeedOutOfRange,
+                vec![entity],
+                format!("velocity {}", velocity.0),
+            ));
+        }
+
+        let position_lane = lane_idx_from_screen_pos(&position);
+
+        match active_lane_change {
+            // partway over, a car is in either the lane it left or the one it's moving into
+            Some(active_lane_change) => {
+                let target = active_lane_change.lane_target;
+
+                if !(0..NUM_LANES).contains(&target) {
+                    found.push((
+                        Invariant::LaneChangeTarget,
+                        vec![entity],
+                        format!("moving into lane {target} of {NUM_LANES}"),
+                    ));
+                } else if position_lane != lane.0 && position_lane != target {
+                    found.push((
+                        Invariant::LaneMismatch,
+                        vec![entity],
+                        format!(
+                            "in lane {position_lane} while moving from {} to {target}",
+                            lane.0
+                        ),
+                    ));
+                }
+            }
+            None => {
+                let off_center = position.x - lane_idx_to_center(lane.0).x;
+
+                if position_lane != lane.0 {
+                    found.push((
+                        Invariant::LaneMismatch,
+                        vec![entity],
+                        format!("in lane {position_lane}, assigned to {}", lane.0),
+                    ));
+                } else if off_center.abs() > INVARIANT_LANE_CENTER_TOLERANCE {
+                    found.push((
+                        Invariant::OffCenter,
+                        vec![entity],
+                        format!("{off_center:.1} from the center of lane {}", lane.0),
+                    ));
+                }
+            }
+        }
+    }
+
+    // every pair once; a car in contact with the one ahead has registered the collision, so
+    // that overlap is expected
+    let cars: Vec<(Entity, Vec2, Vec2, bool)> = query
+        .iter()
+        .map(|(entity, transform, _, _, agent, _)| {
+            (
+                entity,
+                transform.translation.truncate(),
+                transform.scale.truncate(),
+                agent.collision_information.in_contact,
+            )
+        })
+        .collect();
+
+    for (i, (entity_a, position_a, size_a, contact_a)) in cars.iter().enumerate() {
+        for (entity_b, position_b, size_b, contact_b) in &cars[i + 1..] {
+            let overlapping = (*position_a - *position_b)
+                .abs()
+                .cmplt((*size_a + *size_b) / 2.)
+                .all();
+
+            if overlapping && !contact_a && !contact_b {
+                let mut entities = vec![*entity_a, *entity_b];
+                entities.sort();
+                found.push((
+                    Invariant::Overlap,
+                    entities,
+                    format!("centers {:.1} apart", position_a.distance(*position_b)),
+                ));
+            }
+        }
+    }
+
+    let mut still_active = HashSet::new();
+
+    for (invariant, entities, detail) in found {
+        let key = (invariant, entities.clone());
+
+        if !checks.active.contains(&key) {
+            warn!(
+                "tick {} ({:.2}s): {:?} violated by {:?}: {detail}",
+                clock.ticks, clock.elapsed, invariant, entities
+            );
+
+            checks.total += 1;
+            checks.violations.push_back(InvariantViolation {
+                tick: clock.ticks,
+                time: clock.elapsed,
+                invariant,
+                entities,
+                detail,
+            });
+            while checks.violations.len() > INVARIANT_VIOLATIONS_LENGTH {
+                checks.violations.pop_front();
+            }
+        }
+
+        still_active.insert(key);
+    }
+
+    checks.active = still_active;
+}
@@ -0,0 +1,133 @@
+use bevy::prelude::*;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::events::*;
+use crate::resources::*;
+use crate::util::*;
+
+pub fn simulation_clock_system(mut clock: ResMut<SimulationClock>, time: Res<Time>) {
+    clock.elapsed += time.delta_seconds();
+    clock.ticks += 1;
+}
+
+pub fn metrics_sample_system(
+    clock: Res<SimulationClock>,
+    mut metrics: ResMut<TrafficMetrics>,
+    mut merge_reader: EventReader<MergeInteractionEvent>,
+    mut state_change_reader: EventReader<DriverStateChangeEvent>,
+    query: Query<(&Velocity, &LaneEntity), With<Car>>,
+) {
+    metrics.total_lane_changes += merge_reader
+        .read()
+        .filter(|event| event.interaction == MergeInteraction::Merged)
+        .count();
+    metrics.total_crashes += state_change_reader
+        .read()
+        .filter(|change| change.to == DriverState::Crashed)
+        .count();
+
+    let is_due = metrics
+        .samples
+        .back()
+        .is_none_or(|last| clock.elapsed - last.time >= METRICS_SAMPLE_PERIOD);
+    if !is_due {
+        return;
+    }
+
+    let num_cars = query.iter().len();
+    let lane_length = (TOP_WALL - BOTTOM_WALL) * NUM_LANES as f32;
+
+    let mean_speed = if num_cars > 0 {
+        query.iter().map(|(velocity, _)| velocity.y).sum::<f32>() / num_cars as f32
+    } else {
+        0.
+    };
+
+    let mut cars_per_lane = vec![0; NUM_LANES as usize];
+    for (_, lane) in &query {
+        if let Some(count) = cars_per_lane.get_mut(lane.0 as usize) {
+            *count += 1;
+        }
+    }
+
+    // lane changes over the last minute, or over however long the simulation has run if shorter
+    let total_lane_changes = metrics.total_lane_changes;
+    metrics
+        .lane_changes
+        .push((clock.elapsed, total_lane_changes));
+
+    let (since, lane_changes_then) = metrics
+        .lane_changes
+        .iter()
+        .find(|(time, _)| *time >= clock.elapsed - 60.)
+        .copied()
+        .unwrap_or((clock.elapsed, total_lane_changes));
+    let lane_changes_per_minute = if clock.elapsed > since {
+        (total_lane_changes - lane_changes_then) as f32 * 60. / (clock.elapsed - since)
+    } else {
+        0.
+    };
+
+    let density = num_cars as f32 / lane_length;
+    let crashes = metrics.total_crashes;
+
+    metrics.samples.push_back(MetricsSample {
+        time: clock.elapsed,
+        mean_speed,
+        density: density * 1000.,
+        flow: density * mean_speed * 60.,
+        cars_per_lane,
+        lane_changes_per_minute,
+        crashes,
+    });
+
+    while metrics
+        .samples
+        .front()
+        .is_some_and(|oldest| clock.elapsed - oldest.time > METRICS_HISTORY)
+    {
+        metrics.samples.pop_front();
+    }
+
+    let oldest_kept = clock.elapsed - 60.;
+    metrics
+        .lane_changes
+        .retain(|(time, _)| *time >= oldest_kept);
+}
+
+pub fn trajectory_sample_system(
+    clock: Res<SimulationClock>,
+    mut trajectories: ResMut<Trajectories>,
+    query: Query<(Entity, &Transform, &Velocity, &LaneEntity), With<Car>>,
+) {
+    let is_due = trajectories
+        .last_sample_time
+        .is_none_or(|last| clock.elapsed - last >= TRAJECTORY_SAMPLE_PERIOD);
+    if !is_due {
+        return;
+    }
+    trajectories.last_sample_time = Some(clock.elapsed);
+
+    for (entity, transform, velocity, lane) in &query {
+        trajectories
+            .vehicles
+            .entry(entity)
+            .or_default()
+            .push_back(TrajectoryPoint {
+                time: clock.elapsed,
+                position: transform.translation.y,
+                speed: velocity.y,
+                lane: lane.0,
+            });
+    }
+
+    // forget the oldest points, and vehicles that are gone entirely
+    let oldest_kept = clock.elapsed - TRAJECTORY_HISTORY;
+    trajectories.vehicles.retain(|_, points| {
+        while points.front().is_some_and(|point| point.time < oldest_kept) {
+            points.pop_front();
+        }
+        !points.is_empty()
+    });
+}
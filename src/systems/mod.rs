@@ -1,9 +1,26 @@
+pub mod breakpoints;
+pub mod camera;
 pub mod car_spawn_system;
 pub mod event_listeners;
+pub mod heatmap;
 pub mod input;
+pub mod invariants;
+pub mod metrics;
+pub mod overlays;
+pub mod perception;
+pub mod speed;
+#[allow(clippy::module_inception)]
 pub mod systems;
 
+pub use breakpoints::*;
+pub use camera::*;
 pub use car_spawn_system::*;
 pub use event_listeners::*;
+pub use heatmap::*;
 pub use input::*;
+pub use invariants::*;
+pub use metrics::*;
+pub use overlays::*;
+pub use perception::*;
+pub use speed::*;
 pub use systems::*;
@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::resources::*;
+use crate::util::*;
+
+pub fn trail_sample_system(
+    clock: Res<SimulationClock>,
+    mut trails: ResMut<Trails>,
+    query: Query<(Entity, &Transform), With<Car>>,
+) {
+    let is_due = trails
+        .last_sample_time
+        .is_none_or(|last| clock.elapsed - last >= TRAIL_SAMPLE_PERIOD);
+    if !is_due {
+        return;
+    }
+    trails.last_sample_time = Some(clock.elapsed);
+
+    for (entity, transform) in &query {
+        trails
+            .vehicles
+            .entry(entity)
+            .or_default()
+            .push_back((clock.elapsed, transform.translation.truncate()));
+    }
+
+    let oldest_kept = clock.elapsed - TRAIL_MAX_LENGTH;
+    trails.vehicles.retain(|_, points| {
+        while points.front().is_some_and(|(time, _)| *time < oldest_kept) {
+            points.pop_front();
+        }
+        !points.is_empty()
+    });
+}
+
+pub fn draw_vehicle_overlays(
+    overlays: Res<VehicleOverlays>,
+    trails: Res<Trails>,
+    clock: Res<SimulationClock>,
+    fixed_time: Res<Time<Fixed>>,
+    query: Query<
+        (
+            Entity,
+            &Transform,
+            &Velocity,
+            &DriverAgent,
+            Option<&ActiveLaneChange>,
+            Has<SelectedEntity>,
+        ),
+        With<Car>,
+    >,
+    mut gizmos: Gizmos,
+) {
+    let is_shown = |scope: OverlayScope, selected: bool| match scope {
+        OverlayScope::Off => false,
+        OverlayScope::Selected => selected,
+        OverlayScope::All => true,
+    };
+
+    for (entity, transform, velocity, agent, active_lane_change, selected) in &query {
+        let center = transform.translation.truncate();
+
+        if is_shown(overlays.trails, selected) {
+            if let Some(points) = trails.vehicles.get(&entity) {
+                draw_trail(&mut gizmos, points, clock.elapsed, overlays.trail_length);
+            }
+        }
+
+        if is_shown(overlays.predicted_path, selected) {
+            if let Some(active_lane_change) = active_lane_change {
+                // the car slides sideways at `CAR_GAS_POWER` until it's centered in the target
+                // lane, all the while keeping its forward speed
+                let target_x = lane_idx_to_center(active_lane_change.lane_target).x;
+                let seconds_to_center = (target_x - center.x).abs() / CAR_GAS_POWER;
+                let end = Vec2::new(target_x, center.y + velocity.y * seconds_to_center);
+
+                gizmos.line_2d(center, end, PREDICTED_PATH_COLOR);
+                gizmos.rect_2d(end, 0., CAR_SIZE.truncate(), PREDICTED_PATH_COLOR);
+            }
+        }
+
+        if is_shown(overlays.stopping_envelope, selected) {
+            // brake power is applied once per fixed tick, so full braking decelerates at
+            // `CAR_BRAKE_POWER` per timestep
+            let deceleration = CAR_BRAKE_POWER / fixed_time.timestep().as_secs_f32();
+            let stopping_distance = velocity.y.max(0.).powi(2) / (2. * deceleration);
+            if stopping_distance < 1. {
+                continue;
+            }
+
+            let front_distance = agent.collision_information.front_distance;
+            let color = if front_distance >= 0. && front_distance < stopping_distance {
+                STOPPING_ENVELOPE_OVERLAP_COLOR
+            } else {
+                STOPPING_ENVELOPE_COLOR
+            };
+
+            let front = get_car_front_middle(transform);
+            gizmos.rect_2d(
+                Vec2::new(front.x, front.y + stopping_distance / 2.),
+                0.,
+                Vec2::new(CAR_SIZE.x, stopping_distance),
+                color,
+            );
+        }
+    }
+}
+
+fn draw_trail(gizmos: &mut Gizmos, points: &VecDeque<(f32, Vec2)>, now: f32, trail_length: f32) {
+    let road_length = TOP_WALL - BOTTOM_WALL;
+
+    for ((_, from), (time, to)) in points.iter().zip(points.iter().skip(1)) {
+        let age = now - time;
+        if age > trail_length {
+            continue;
+        }
+
+        // the car wrapped from the top of the road back to the bottom
+        if (to.y - from.y).abs() > road_length / 2. {
+            continue;
+        }
+
+        let fade = 1. - age / trail_length;
+        gizmos.line_2d(*from, *to, TRAIL_COLOR.with_a(fade));
+    }
+}
@@ -0,0 +1,205 @@
+use bevy::prelude::*;
+
+use crate::components::*;
+use crate::constants::*;
+use crate::resources::*;
+use crate::util::*;
+
+pub fn perception_error_system(
+    config: Res<PerceptionNoiseConfig>,
+    mut rng: ResMut<SimulationRng>,
+    mut query: Query<(Entity, &mut DriverAgent)>,
+) {
+    // each driver's error follows a first-order autoregressive process: it keeps most of last
+    // tick's value and takes a small fresh step, staying at unit variance overall
+    if !config.enabled {
+        return;
+    }
+
+    let step = (1. - config.correlation * config.correlation).sqrt();
+
+    // in entity order, so a restored world hands each car the same random numbers
+    let mut agents: Vec<_> = query.iter_mut().collect();
+    agents.sort_by_key(|(entity, _)| *entity);
+
+    for (_, mut agent) in agents {
+        let error = &mut agent.estimation_error;
+        error.distance = config.correlation * error.distance + step * gaussian(&mut rng.0);
+        error.speed = config.correlation * error.speed + step * gaussian(&mut rng.0);
+    }
+}
+
+pub fn perceive_distance(
+    agent: &DriverAgent,
+    distance: f32,
+    config: &PerceptionNoiseConfig,
+) -> f32 {
+    // biased by the driver's judgment, with noise proportional to the distance being judged
+    if !config.enabled {
+        return distance;
+    }
+
+    let noise_pct = config.distance_noise_pct * driver_judgment_noise_pct(&agent.judgment);
+    let noise = distance.abs() * noise_pct * agent.estimation_error.distance;
+
+    distance * driver_judgment_gap_bias(&agent.judgment) + noise
+}
+
+pub fn perceive_relative_speed(
+    agent: &DriverAgent,
+    relative_speed: f32,
+    distance: f32,
+    config: &PerceptionNoiseConfig,
+) -> f32 {
+    // closing speed is harder to read the farther away the other car is
+    if !config.enabled {
+        return relative_speed;
+    }
+
+    let distance_pct = (distance.abs() / CAR_SIGHT_DISTANCE).min(1.);
+    let noise = config.speed_noise
+        * driver_judgment_noise_pct(&agent.judgment)
+        * distance_pct
+        * agent.estimation_error.speed;
+
+    relative_speed + noise
+}
+
+pub fn perception_system(
+    config: Res<PerceptionConfig>,
+    noise_config: Res<PerceptionNoiseConfig>,
+    mut query: Query<(Entity, &Transform, &Velocity, &DriverAgent, &mut Perception), With<Car>>,
+    others: Query<(Entity, &Transform, &Velocity), With<Car>>,
+) {
+    // each car sweeps its forward, rear and side sensors over every other car within reach;
+    // only cars in its own or adjacent lanes (within `side_range`) can ever be seen
+    for (entity, transform, velocity, agent, mut perception) in &mut query {
+        perception.detections.clear();
+
+        let position = transform.translation.truncate();
+
+        for (other_entity, other_transform, other_velocity) in &others {
+            if entity == other_entity {
+                continue;
+            }
+
+            let offset = other_transform.translation.truncate() - position;
+
+            if offset.x.abs() >= config.side_range {
+                continue;
+            }
+
+            // bumper-to-bumper distance along the road; negative when the two cars overlap
+            let gap = offset.y.abs() - CAR_SIZE.y;
+
+            let sensor = if gap < 0. {
+                Sensor::Side
+            } else if offset.y > 0. {
+                Sensor::Forward
+            } else {
+                Sensor::Rear
+            };
+
+            let range = match sensor {
+                Sensor::Forward => config.forward_range,
+                Sensor::Rear => config.rear_range,
+                Sensor::Side => f32::MAX,
+            };
+
+            if gap > range {
+                continue;
+            }
+
+            // the blind spot is the rear quarter of the lanes to either side: beside the car, up to
+            // `blind_spot_length` behind its rear bumper
+            let is_adjacent_lane = offset.x.abs() >= LANE_WIDTH / 2.;
+            let in_blind_spot =
+                is_adjacent_lane && offset.y < 0. && gap <= config.blind_spot_length;
+
+            // sensors report what the driver believes; which sensor and blind spot are geometry
+            let relative_speed = other_velocity.y - velocity.y;
+
+            perception.detections.push(Detection {
+                entity: other_entity,
+                sensor,
+                lanes: lanes_occupied(other_transform),
+                gap: perceive_distance(agent, gap, &noise_config),
+                relative_speed: perceive_relative_speed(agent, relative_speed, gap, &noise_config),
+                in_blind_spot,
+            });
+        }
+    }
+}
+
+pub fn check_lane(
+    target_lane: i32,
+    perception: &Perception,
+    checked_blind_spot: bool,
+) -> LaneCheck {
+    // whether the driver believes it can move into `target_lane`, judged only from what its
+    // sensors picked up; a driver that skipped its blind spot check won't see cars sitting there
+    let mut missed_blind_spot = false;
+
+    for detection in &perception.detections {
+        if !detection.lanes.contains(&target_lane) {
+            continue;
+        }
+
+        if detection.in_blind_spot && !checked_blind_spot {
+            missed_blind_spot = true;
+            continue;
+        }
+
+        // relative speed is positive when the other car is pulling away from this one
+        let is_clear = match detection.sensor {
+            Sensor::Side => false,
+            Sensor::Forward => {
+                detection.gap + f32::min(detection.relative_speed, 0.) * LANE_CHANGE_DURATION
+                    >= CAR_SIZE_HALF.y
+            }
+            Sensor::Rear => {
+                detection.gap - f32::max(detection.relative_speed, 0.) * LANE_CHANGE_DURATION
+                    >= CAR_SIZE_HALF.y
+            }
+        };
+
+        if !is_clear {
+            return LaneCheck::Blocked;
+        }
+    }
+
+    if missed_blind_spot {
+        LaneCheck::MissedBlindSpot
+    } else {
+        LaneCheck::Open
+    }
+}
+
+pub fn draw_perception_ranges(
+    config: Res<PerceptionConfig>,
+    query: Query<&Transform, With<Perception>>,
+    mut gizmos: Gizmos,
+) {
+    for transform in &query {
+        let rear_middle = Vec2::new(
+            transform.translation.x,
+            transform.translation.y - CAR_SIZE_HALF.y,
+        );
+
+        gizmos.ray_2d(rear_middle, Vec2::NEG_Y * config.rear_range, Color::TEAL);
+
+        for side in [-1., 1.] {
+            let blind_spot_center = Vec2::new(
+                transform.translation.x + side * LANE_WIDTH,
+                rear_middle.y - config.blind_spot_length / 2.,
+            );
+
+            gizmos.rect_2d(
+                blind_spot_center,
+                0.,
+                Vec2::new(CAR_SIZE.x, config.blind_spot_length),
+                Color::rgba(1., 0.5, 0., 0.4),
+            );
+        }
+    }
+}
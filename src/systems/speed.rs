@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+use crate::constants::*;
+use crate::resources::*;
+use crate::util::*;
+
+pub fn simulation_speed_system(
+    mut speed: ResMut<SimulationSpeed>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    real_time: Res<Time<Real>>,
+    fixed_time: Res<Time<Fixed>>,
+    clock: Res<SimulationClock>,
+    pause_state: Res<State<PauseState>>,
+) {
+    // the speed is set by scaling virtual time, which the fixed ticks are paced by; a faster
+    // speed only means more ticks per frame, each of them the same length as ever
+    let frame_time = real_time.delta_seconds();
+
+    if frame_time > 0. {
+        // rewinding or loading can move the clock backwards; that isn't a negative speed
+        let reading = f32::max(clock.elapsed - speed.last_elapsed, 0.) / frame_time;
+        speed.measured += (reading - speed.measured) * SPEED_RATE_SMOOTHING;
+    }
+    speed.last_elapsed = clock.elapsed;
+
+    match speed.multiplier() {
+        Some(multiplier) => {
+            virtual_time.set_relative_speed(multiplier);
+            virtual_time.set_max_delta(Duration::from_secs_f32(SPEED_MAX_DELTA));
+        }
+        None => {
+            // scale the ticks per frame toward what fits in the frame budget, at most doubling or
+            // halving at a time; paused frames say nothing about how long ticks take
+            if pause_state.get() == &PauseState::Running && frame_time > 0. {
+                let ticks = speed.ticks_per_frame as f32;
+                let target =
+                    (ticks * FAST_FORWARD_FRAME_BUDGET / frame_time).clamp(ticks / 2., ticks * 2.);
+                speed.ticks_per_frame = (target.round() as u32).clamp(1, FAST_FORWARD_MAX_TICKS);
+            }
+
+            // virtual time runs far ahead and is cut off at exactly that many ticks
+            let max_delta = fixed_time.timestep() * speed.ticks_per_frame;
+            virtual_time.set_max_delta(max_delta);
+            virtual_time.set_relative_speed_f64(
+                max_delta.as_secs_f64() / f64::max(real_time.delta_seconds_f64(), 1e-3) * 2.,
+            );
+        }
+    }
+}
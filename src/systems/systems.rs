@@ -1,22 +1,28 @@
 use bevy::{
-    math::bounding::{Aabb2d, Bounded2d, BoundingVolume, IntersectsVolume, RayCast2d},
+    math::bounding::{Aabb2d, RayCast2d},
     prelude::*,
-    render::primitives::Aabb,
     utils::hashbrown::HashMap,
     window::PrimaryWindow,
 };
+use rand::Rng;
 
 use crate::components::*;
 use crate::constants::*;
 use crate::events::*;
 use crate::resources::*;
+use crate::systems::perception::*;
 use crate::util::*;
 
 pub fn debug_mouse_system(
     cursor_coords: ResMut<CursorWorldCoords>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
     mut query: Query<(&mut Text, &mut Style), With<MouseText>>,
 ) {
-    let text_position = screen_space_to_world_coords(&cursor_coords.0);
+    // the text follows the cursor in window coordinates, which no longer line up with world
+    // coordinates once the camera moves or zooms
+    let Some(text_position) = q_window.single().cursor_position() else {
+        return;
+    };
 
     let (mut text, mut style) = query.single_mut();
 
@@ -69,6 +75,61 @@ pub fn apply_velocity(mut query: Query<(&mut Transform, &Velocity)>, time: Res<T
     }
 }
 
+pub fn measure_acceleration_system(
+    mut query: Query<(&Velocity, &mut Acceleration)>,
+    time: Res<Time>,
+) {
+    // runs last in the tick, so it sees the combined effect of driving, friction and collisions
+    for (velocity, mut acceleration) in &mut query {
+        if time.delta_seconds() > 0. {
+            acceleration.value = (velocity.y - acceleration.last_speed) / time.delta_seconds();
+        }
+        acceleration.last_speed = velocity.y;
+    }
+}
+
+pub fn vehicle_color_system(
+    color_mode: Res<State<ColorMode>>,
+    mut vehicle_materials: ResMut<VehicleMaterials>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut query: Query<
+        (
+            &mut Handle<ColorMaterial>,
+            &Velocity,
+            &Acceleration,
+            &DriverAgent,
+            &VehicleClass,
+        ),
+        With<Car>,
+    >,
+) {
+    // points each car at the shared material for its bucket; the legend lists buckets in the
+    // same order the enums declare their variants
+    let mode = *color_mode.get();
+    let buckets = color_mode_buckets(&mode);
+
+    for (mut handle, velocity, acceleration, agent, vehicle_class) in &mut query {
+        let bucket = match mode {
+            ColorMode::Speed => speed_bucket(velocity.y),
+            ColorMode::Acceleration => acceleration_bucket(acceleration.value),
+            ColorMode::Temperament => agent.temperament.clone() as usize,
+            ColorMode::Patience => agent.patience.clone() as usize,
+            ColorMode::Lawfulness => agent.lawfulness.clone() as usize,
+            ColorMode::DriverState => agent.driver_state as usize,
+            ColorMode::VehicleClass => *vehicle_class as usize,
+        };
+
+        let shared = vehicle_materials
+            .handles
+            .entry((mode, bucket))
+            .or_insert_with(|| materials.add(ColorMaterial::from(buckets[bucket].1)));
+
+        if *handle != *shared {
+            *handle = shared.clone();
+        }
+    }
+}
+
 pub fn wrap_position(mut query: Query<&mut Transform, With<Velocity>>) {
     for mut transform in &mut query {
         if transform.translation.y > TOP_WALL - (transform.scale.y / 2.0) {
@@ -96,6 +157,12 @@ pub fn draw_car_sight_lines(
 
         let collision_distance = agent.collision_information.front_distance;
 
+        gizmos.rect_2d(
+            transform.translation.truncate(),
+            0.,
+            CAR_SIZE.truncate() + Vec2::splat(4.),
+            driver_state_color(&agent.driver_state),
+        );
         gizmos.ray_2d(line_start, Vec2::Y * CAR_SIGHT_DISTANCE, Color::GREEN);
         gizmos.ray_2d(line_start, Vec2::Y * tail_threshold, Color::MAROON);
         gizmos.ray_2d(
@@ -103,80 +170,474 @@ pub fn draw_car_sight_lines(
             Vec2::Y * collision_distance,
             Color::GOLD,
         );
+
+        // the vehicles beyond the immediate leader that feed anticipation
+        for vehicle_ahead in agent.collision_information.vehicles_ahead.iter().skip(1) {
+            gizmos.circle_2d(
+                Vec2::new(line_start.x, line_start.y + vehicle_ahead.distance),
+                4.,
+                Color::SILVER,
+            );
+        }
+    }
+}
+
+pub fn draw_turn_signals(query: Query<(&Transform, &TurnSignal)>, mut gizmos: Gizmos) {
+    for (transform, turn_signal) in &query {
+        // blink on for one period, off for the next
+        if (turn_signal.elapsed / TURN_SIGNAL_BLINK_PERIOD) as i32 % 2 != 0 {
+            continue;
+        }
+
+        let side = match turn_signal.direction {
+            LaneChangeDirection::Left => -1.,
+            LaneChangeDirection::Right => 1.,
+            LaneChangeDirection::None => continue,
+        };
+
+        let center = transform.translation.truncate();
+        let corner_x = center.x + side * CAR_SIZE_HALF.x;
+
+        gizmos.circle_2d(
+            Vec2::new(corner_x, center.y + CAR_SIZE_HALF.y),
+            3.,
+            TURN_SIGNAL_COLOR,
+        );
+        gizmos.circle_2d(
+            Vec2::new(corner_x, center.y - CAR_SIZE_HALF.y),
+            3.,
+            TURN_SIGNAL_COLOR,
+        );
     }
 }
 
 pub fn agent_drive_system(
-    mut query: Query<(&mut DriverAgent, &mut Velocity, &Transform)>,
+    mut query: Query<
+        (
+            Entity,
+            &mut DriverAgent,
+            &mut Velocity,
+            Option<&CourtesyResponse>,
+        ),
+        Without<Frozen>,
+    >,
+    car_following_config: Res<CarFollowingConfig>,
+    mut decision_trace: ResMut<DecisionTrace>,
+    clock: Res<SimulationClock>,
     time: Res<Time>,
 ) {
-    for (mut agent, mut velocity, transform) in &mut query {
-        agent_accelerate_or_brake(&mut agent, &mut velocity, &time);
+    for (entity, mut agent, mut velocity, courtesy_response) in &mut query {
+        let previous_speed = velocity.y;
+
+        let mut decision = match agent.driver_state {
+            // a crashed car stays where it is until the car it hit moves off
+            DriverState::Crashed => {
+                velocity.y = 0.;
+                Decision::new(DecisionBranch::Crashed, vec![("speed", previous_speed)])
+            }
+            DriverState::Cruising
+            | DriverState::Following
+            | DriverState::Yielding
+            | DriverState::PreparingToPass
+            | DriverState::ChangingLanes
+            | DriverState::Stopped => match courtesy_response {
+                Some(courtesy_response) => {
+                    agent_courtesy_behavior(&mut agent, &mut velocity, courtesy_response, &time)
+                }
+                None => agent_accelerate_or_brake(&mut agent, &mut velocity, &time),
+            },
+        };
+
+        if car_following_config.anticipation {
+            // ease off early for a slowdown further up the lane, unless the car directly ahead
+            // already has this driver braking harder
+            if let Some(brake_power) = anticipation_brake_power(&agent, previous_speed) {
+                if velocity.y - previous_speed > -brake_power {
+                    velocity.y = f32::max(previous_speed - brake_power, 0.);
+                    decision.branch = DecisionBranch::Anticipate;
+                    decision
+                        .inputs
+                        .push(("anticipation_brake_power", brake_power));
+                }
+            }
+        }
 
-        // match agent.driver_state {
-        //     DriverState::Normal => agent_normal_behavior(&mut agent, &mut velocity, &transform),
-        //     DriverState::ChangingLanes => {
-        //         agent_changing_lanes_behavior(&mut agent, &mut velocity, &transform)
-        //     }
-        // }
+        let acceleration = (velocity.y - previous_speed) / time.delta_seconds();
+        decision_trace.record(entity, &clock, decision, Some(acceleration));
     }
+
+    decision_trace.prune(clock.elapsed);
 }
 
-pub fn agent_check_lane_change_system(
-    mut commands: Commands,
-    query: Query<(Entity, &mut DriverAgent, &Velocity, &Transform, &LaneEntity)>,
+fn anticipation_brake_power(agent: &DriverAgent, speed: f32) -> Option<f32> {
+    // looks past the immediate leader (handled by `brake_for_front`) at the vehicles beyond it;
+    // for each, estimate how long until this car closes to its usual tail distance behind the
+    // queue in front of that vehicle, and brake gently if that's within the anticipation horizon.
+    // farther vehicles are less certain, so they count for proportionally less
+    let min_tail_distance = CAR_SIZE.y * driver_temperament_tail_threshold(&agent.temperament);
+
+    agent
+        .collision_information
+        .vehicles_ahead
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter_map(|(position, vehicle_ahead)| {
+            let closing_speed = speed - vehicle_ahead.speed;
+            if closing_speed <= 0. {
+                return None;
+            }
+
+            // room for this car's tail distance behind every car between here and there
+            let queue_length = position as f32 * (CAR_SIZE.y + min_tail_distance);
+            let time_to_close = f32::max(vehicle_ahead.distance - queue_length, 0.) / closing_speed;
+
+            if time_to_close >= ANTICIPATION_HORIZON {
+                return None;
+            }
+
+            let pressure = 1. - time_to_close / ANTICIPATION_HORIZON;
+            Some(CAR_BRAKE_POWER * ANTICIPATION_BRAKE_PCT * pressure / position as f32)
+        })
+        .reduce(f32::max)
+}
+
+pub fn agent_state_system(
+    mut query: Query<(
+        Entity,
+        &mut DriverAgent,
+        &Velocity,
+        Option<&ActiveLaneChange>,
+        Option<&CourtesyResponse>,
+    )>,
+    mut state_change_writer: EventWriter<DriverStateChangeEvent>,
 ) {
-    let mut lane_to_transform_map: HashMap<i32, &Transform> = Default::default();
+    for (entity, mut agent, velocity, active_lane_change, courtesy_response) in &mut query {
+        let is_opening_gap = courtesy_response
+            .is_some_and(|courtesy_response| courtesy_response.response == MergeResponse::OpenGap);
+
+        let next_state = next_driver_state(
+            &agent,
+            velocity,
+            active_lane_change.is_some(),
+            is_opening_gap,
+        );
 
-    for (_, _, _, transform, lane) in &query {
-        lane_to_transform_map.insert(lane.0, &transform);
+        if next_state != agent.driver_state {
+            state_change_writer.send(DriverStateChangeEvent {
+                entity,
+                from: agent.driver_state,
+                to: next_state,
+            });
+
+            agent.driver_state = next_state;
+        }
     }
+}
 
-    for (entity, agent, velocity, transform, lane) in &query {
-        match get_lane_change_direction(&agent, &velocity, lane.0) {
-            LaneChangeDirection::Left => {
-                attempt_lane_change(
+fn next_driver_state(
+    agent: &DriverAgent,
+    velocity: &Velocity,
+    is_changing_lanes: bool,
+    is_opening_gap: bool,
+) -> DriverState {
+    // states are checked from most to least urgent; the first one whose entry condition holds wins,
+    // so a state is exited as soon as its own condition stops holding or a more urgent one applies
+    let distance = agent.collision_information.front_distance;
+    let has_obstacle = has_obstacle_in_range(agent);
+
+    let min_tail_distance = CAR_SIZE.y * driver_temperament_tail_threshold(&agent.temperament);
+    let brake_distance_threshold =
+        CAR_SIGHT_DISTANCE * driver_temperament_brake_threshold(&agent.temperament);
+
+    if agent.collision_information.in_contact {
+        DriverState::Crashed
+    } else if is_changing_lanes {
+        DriverState::ChangingLanes
+    } else if velocity.y < CAR_STOPPED_SPEED {
+        DriverState::Stopped
+    } else if wants_to_pass(agent, velocity) {
+        DriverState::PreparingToPass
+    } else if is_opening_gap || (has_obstacle && distance <= min_tail_distance) {
+        DriverState::Yielding
+    } else if has_obstacle && distance <= brake_distance_threshold {
+        DriverState::Following
+    } else {
+        DriverState::Cruising
+    }
+}
+
+pub fn agent_check_lane_change_system(
+    mut commands: Commands,
+    query: Query<
+        (
+            Entity,
+            &DriverAgent,
+            &Velocity,
+            &LaneEntity,
+            Option<&TurnSignal>,
+        ),
+        (Without<ActiveLaneChange>, Without<Frozen>),
+    >,
+    mut merge_writer: EventWriter<MergeInteractionEvent>,
+    mut rng: ResMut<SimulationRng>,
+    mut decision_trace: ResMut<DecisionTrace>,
+    clock: Res<SimulationClock>,
+) {
+    // decides which way each driver wants to go and signals it; the move itself only starts once
+    // `agent_turn_signal_system` has seen the signal on long enough and the target lane is open
+    // in entity order, so a restored world hands each car the same random numbers
+    let mut drivers: Vec<_> = query.iter().collect();
+    drivers.sort_by_key(|(entity, ..)| *entity);
+
+    for (entity, agent, velocity, lane, turn_signal) in drivers {
+        let direction = get_lane_change_direction(agent, velocity, lane.0);
+
+        if let Some(turn_signal) = turn_signal {
+            // changed their mind; turn the signal back off
+            if turn_signal.direction != direction {
+                commands.entity(entity).remove::<TurnSignal>();
+                decision_trace.record(
                     entity,
-                    LaneChangeDirection::Left,
-                    &transform,
-                    lane.0,
-                    &lane_to_transform_map,
-                    &mut commands,
+                    &clock,
+                    Decision::new(
+                        DecisionBranch::ChangeOfMind,
+                        vec![
+                            ("speed", velocity.y),
+                            ("lane", lane.0 as f32),
+                            ("lane_target", turn_signal.lane_target as f32),
+                        ],
+                    ),
+                    None,
                 );
+                merge_writer.send(MergeInteractionEvent {
+                    merging_entity: entity,
+                    responder: None,
+                    interaction: MergeInteraction::Abandoned,
+                });
             }
-            LaneChangeDirection::Right => {
-                attempt_lane_change(
-                    entity,
-                    LaneChangeDirection::Right,
-                    &transform,
-                    lane.0,
-                    &lane_to_transform_map,
-                    &mut commands,
-                );
+
+            continue;
+        }
+
+        let lane_target = match direction {
+            LaneChangeDirection::Left => lane.0 - 1,
+            LaneChangeDirection::Right => lane.0 + 1,
+            LaneChangeDirection::None => continue,
+        };
+
+        if !(0..NUM_LANES).contains(&lane_target) {
+            continue;
+        }
+
+        let blind_spot_miss_pct = driver_attentiveness_blind_spot_miss_pct(&agent.attentiveness);
+        let checked_blind_spot = !rng.0.gen_bool(blind_spot_miss_pct);
+
+        commands.entity(entity).insert(TurnSignal {
+            direction,
+            lane_target,
+            elapsed: 0.,
+            checked_blind_spot,
+        });
+        decision_trace.record(
+            entity,
+            &clock,
+            Decision::new(
+                DecisionBranch::Signal,
+                vec![
+                    ("speed", velocity.y),
+                    ("front_distance", agent.collision_information.front_distance),
+                    ("lane", lane.0 as f32),
+                    ("lane_target", lane_target as f32),
+                    ("checked_blind_spot", checked_blind_spot as u8 as f32),
+                ],
+            ),
+            None,
+        );
+        merge_writer.send(MergeInteractionEvent {
+            merging_entity: entity,
+            responder: None,
+            interaction: MergeInteraction::Signaled,
+        });
+    }
+}
+
+pub fn agent_turn_signal_system(
+    mut commands: Commands,
+    mut query: Query<
+        (Entity, &Perception, &mut TurnSignal),
+        (Without<ActiveLaneChange>, Without<Frozen>),
+    >,
+    mut merge_writer: EventWriter<MergeInteractionEvent>,
+    mut decision_trace: ResMut<DecisionTrace>,
+    clock: Res<SimulationClock>,
+    time: Res<Time>,
+) {
+    for (entity, perception, mut turn_signal) in &mut query {
+        turn_signal.elapsed += time.delta_seconds();
+
+        if turn_signal.elapsed < TURN_SIGNAL_MIN_DURATION {
+            continue;
+        }
+
+        let lane_check = check_lane(
+            turn_signal.lane_target,
+            perception,
+            turn_signal.checked_blind_spot,
+        );
+
+        if lane_check == LaneCheck::MissedBlindSpot {
+            debug!(
+                "{:?} is moving into lane {} without seeing the car in its blind spot",
+                entity, turn_signal.lane_target
+            );
+        }
+
+        let branch = match lane_check {
+            LaneCheck::Open => DecisionBranch::Merge,
+            LaneCheck::MissedBlindSpot => DecisionBranch::MergeBlind,
+            LaneCheck::Blocked if turn_signal.elapsed > TURN_SIGNAL_TIMEOUT => {
+                DecisionBranch::GiveUp
             }
-            LaneChangeDirection::None => {}
+            LaneCheck::Blocked => DecisionBranch::WaitForGap,
+        };
+        decision_trace.record(
+            entity,
+            &clock,
+            Decision::new(
+                branch,
+                vec![
+                    ("signal_elapsed", turn_signal.elapsed),
+                    ("lane_target", turn_signal.lane_target as f32),
+                    (
+                        "checked_blind_spot",
+                        turn_signal.checked_blind_spot as u8 as f32,
+                    ),
+                ],
+            ),
+            None,
+        );
+
+        if lane_check != LaneCheck::Blocked {
+            // adding the ActiveLangeChange component means this entity will be
+            // picked up by the LaneChangeSystem and its velocity modified; the
+            // signal stays on until the car is centered in its new lane
+            commands.entity(entity).insert(ActiveLaneChange {
+                lane_change_direction: turn_signal.direction.clone(),
+                lane_target: turn_signal.lane_target,
+            });
+            merge_writer.send(MergeInteractionEvent {
+                merging_entity: entity,
+                responder: None,
+                interaction: MergeInteraction::Merged,
+            });
+        } else if turn_signal.elapsed > TURN_SIGNAL_TIMEOUT {
+            // nobody let them in; give up (they'll signal again if they still want the lane)
+            commands.entity(entity).remove::<TurnSignal>();
+            merge_writer.send(MergeInteractionEvent {
+                merging_entity: entity,
+                responder: None,
+                interaction: MergeInteraction::Abandoned,
+            });
         }
     }
 }
 
+pub fn agent_courtesy_system(
+    mut commands: Commands,
+    signal_query: Query<(Entity, &Transform, &TurnSignal)>,
+    query: Query<
+        (
+            Entity,
+            &DriverAgent,
+            &Transform,
+            &LaneEntity,
+            Option<&CourtesyResponse>,
+        ),
+        With<Car>,
+    >,
+    mut merge_writer: EventWriter<MergeInteractionEvent>,
+) {
+    // only the car directly behind a signaling car in its target lane responds to it
+    let mut followers: HashMap<Entity, (Entity, f32)> = HashMap::new();
+
+    for (merging_entity, merging_transform, turn_signal) in &signal_query {
+        let mut closest_follower: Option<(Entity, f32)> = None;
+
+        for (entity, _, transform, lane, _) in &query {
+            if entity == merging_entity || lane.0 != turn_signal.lane_target {
+                continue;
+            }
+
+            // bumper-to-bumper gap between the follower's front and the merging car's rear
+            let gap = merging_transform.translation.y - transform.translation.y - CAR_SIZE.y;
+
+            if merging_transform.translation.y < transform.translation.y || gap > CAR_SIGHT_DISTANCE
+            {
+                continue;
+            }
+
+            if closest_follower.is_none_or(|(_, closest_gap)| gap < closest_gap) {
+                closest_follower = Some((entity, gap));
+            }
+        }
+
+        if let Some((follower, gap)) = closest_follower {
+            // a follower stuck behind several signaling cars reacts to the nearest one
+            if followers
+                .get(&follower)
+                .is_none_or(|(_, other_gap)| gap < *other_gap)
+            {
+                followers.insert(follower, (merging_entity, gap));
+            }
+        }
+    }
+
+    for (entity, agent, _, _, courtesy_response) in &query {
+        let Some(&(merging_entity, gap)) = followers.get(&entity) else {
+            if courtesy_response.is_some() {
+                commands.entity(entity).remove::<CourtesyResponse>();
+            }
+            continue;
+        };
+
+        let response = driver_temperament_merge_response(&agent.temperament);
+
+        if courtesy_response.is_none_or(|current| current.merging_entity != merging_entity) {
+            merge_writer.send(MergeInteractionEvent {
+                merging_entity,
+                responder: Some(entity),
+                interaction: MergeInteraction::Responded(response.clone()),
+            });
+        }
+
+        commands.entity(entity).insert(CourtesyResponse {
+            merging_entity,
+            response,
+            gap,
+        });
+    }
+}
+
 pub fn agent_active_lane_change_system(
     mut commands: Commands,
-    mut query: Query<(
-        Entity,
-        &mut DriverAgent,
-        &mut Velocity,
-        &Transform,
-        &ActiveLaneChange,
-    )>,
+    mut query: Query<
+        (
+            Entity,
+            &mut Velocity,
+            &Transform,
+            &mut LaneEntity,
+            &ActiveLaneChange,
+        ),
+        Without<Frozen>,
+    >,
 ) {
-    for (entity, mut agent, mut velocity, transform, active_lane_change) in &mut query {
-        // TODO: negative velocity
-        // let current_lane_idx = lane_idx_from_screen_pos(transform.translation);
+    for (entity, mut velocity, transform, mut lane, active_lane_change) in &mut query {
         // println!("moving to target lane {}", active_lane_change.lane_target);
         let target_lane_center = lane_idx_to_center(active_lane_change.lane_target);
         let distance_from_lane_center = target_lane_center.x - transform.translation.x;
 
-        if distance_from_lane_center > 0.5 {
+        if distance_from_lane_center.abs() > 0.5 {
             let vec = Vec3::new(distance_from_lane_center, 0., 0.);
             let normalized = vec.normalize();
             velocity.x = normalized.x * CAR_GAS_POWER;
@@ -185,9 +646,12 @@ pub fn agent_active_lane_change_system(
             //     target_lane_center, velocity.x, distance_from_lane_center
             // );
         } else {
+            // centered in the target lane: the lane change is complete
             velocity.x = 0.;
-            // agent.0 = DriverState::Normal;
-            commands.entity(entity).remove::<ActiveLaneChange>();
+            lane.0 = active_lane_change.lane_target;
+            commands
+                .entity(entity)
+                .remove::<(ActiveLaneChange, TurnSignal)>();
         }
     }
 }
@@ -202,20 +666,16 @@ fn get_lane_change_direction(
     //  1) there's a car in front of them, and they're impatient
     //  2) they're law-abiding and want to move to the right lane when not passing
 
-    let min_speed_threshold = driver_patience_min_speed_pct(&agent.patience);
-
-    if has_obstacle_in_range(&agent) && velocity.y < min_speed_threshold {
+    if wants_to_pass(agent, velocity) {
         // println!(
         //     "I want to pass you! velocity={} threshold={}",
         //     velocity.y, min_speed_threshold
         // );
 
-        return LaneChangeDirection::Left;
+        LaneChangeDirection::Left
     } else {
         match agent.lawfulness {
-            DriverLawfulness::Chaotic => {
-                return LaneChangeDirection::None;
-            }
+            DriverLawfulness::Chaotic => LaneChangeDirection::None,
             DriverLawfulness::Orderly => {
                 if lane_idx < NUM_LANES - 1 {
                     // println!("I want to return to the right lane!");
@@ -224,109 +684,92 @@ fn get_lane_change_direction(
 
                 // println!("Orderly car is already at {} max={}", lane_idx, NUM_LANES);
 
-                return LaneChangeDirection::None;
+                LaneChangeDirection::None
             }
         }
     }
 }
 
-fn attempt_lane_change(
-    entity: Entity,
-    direction: LaneChangeDirection,
-    transform: &Transform,
-    current_lane: i32,
-    lane_to_transform_map: &HashMap<i32, &Transform>,
-    commands: &mut Commands,
-) {
-    let next_lane = match direction {
-        LaneChangeDirection::Left => current_lane - 1,
-        LaneChangeDirection::Right => current_lane + 1,
-        LaneChangeDirection::None => current_lane,
-    };
+fn wants_to_pass(agent: &DriverAgent, velocity: &Velocity) -> bool {
+    // an agent wants to pass when something is ahead of it and it has been slowed below the
+    // fraction of its top speed its patience will put up with
+    let top_speed = SPEED_LIMIT * driver_temperament_top_speed_pct(&agent.temperament);
+    let min_speed_threshold = top_speed * driver_patience_min_speed_pct(&agent.patience);
 
-    if is_lane_open(next_lane, &transform, &lane_to_transform_map) {
-        // println!("Lane {} is open, I want to move there!", next_lane);
-        // adding the ActiveLangeChange component means this entity will be
-        // picked up by the LaneChangeSystem and its velocity modified
-        commands.entity(entity).insert(ActiveLaneChange {
-            lane_change_direction: direction,
-            lane_target: next_lane,
-        });
-    } else {
-        // println!("Lane {} is NOT open, but I want to move there!", next_lane);
-    }
+    has_obstacle_in_range(agent) && velocity.y < min_speed_threshold
 }
 
 fn agent_accelerate_or_brake(
     agent: &mut DriverAgent,
-    mut velocity: &mut Velocity,
+    velocity: &mut Velocity,
     time: &Res<Time>,
-) {
+) -> Decision {
     let top_speed = SPEED_LIMIT * driver_temperament_top_speed_pct(&agent.temperament);
+    let inputs = vec![("speed", velocity.y), ("top_speed", top_speed)];
+
     if velocity.y < top_speed {
-        if has_obstacle_in_range(&agent) {
-            brake_for_front(&agent, &mut velocity, &time);
+        if has_obstacle_in_range(agent) {
+            brake_for_front(agent, velocity, time)
         } else {
             velocity.y += CAR_GAS_POWER;
+            Decision::new(DecisionBranch::ClearRoad, inputs)
         }
-    } // else nothing, friction will let the car roll back to acceptable top speed
-}
-
-fn has_obstacle_in_range(agent: &DriverAgent) -> bool {
-    agent.collision_information.front_distance > -1.
+    } else if has_obstacle_in_range(agent) {
+        // already at top speed, but whatever is ahead can still make the car brake
+        let speed = velocity.y;
+        let decision = brake_for_front(agent, velocity, time);
+        velocity.y = f32::min(velocity.y, speed);
+        decision
+    } else {
+        // nothing, friction will let the car roll back to acceptable top speed
+        Decision::new(DecisionBranch::AtTopSpeed, inputs)
+    }
 }
 
-fn is_lane_open(
-    target_lane: i32,
-    car_pos: &Transform,
-    lane_to_transform_map: &HashMap<i32, &Transform>,
-) -> bool {
-    // draw a box extending two lane widths to either side (will still only check intersection against cars in directly adjacent lane)
-    //    _____
-    //    |   |
-    //    |Car|
-    //    |   |
-    //    ‾‾‾‾‾
-    //    ==>
-    //    _________________
-    //    | ... |   | ... |
-    //    | ... |Car| ... |
-    //    | ... |   | ... |
-    //    ‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾‾
-
-    // create adjusted size box to compare against cars in target lane
-    let bottom_left = Vec2::new(
-        car_pos.translation.x - LANE_WIDTH_DOUBLE,
-        car_pos.translation.y - CAR_SIZE_HALF.y,
-    );
-
-    let top_right = Vec2::new(
-        car_pos.translation.x + LANE_WIDTH_DOUBLE,
-        car_pos.translation.y + CAR_SIZE_HALF.y,
-    );
-
-    let bounding_box = Aabb2d {
-        min: bottom_left,
-        max: top_right,
-    };
-
-    for (lane_idx, &target_transform) in lane_to_transform_map {
-        if *lane_idx != target_lane {
-            continue;
+fn agent_courtesy_behavior(
+    agent: &mut DriverAgent,
+    velocity: &mut Velocity,
+    courtesy_response: &CourtesyResponse,
+    time: &Res<Time>,
+) -> Decision {
+    // reacting to a car that is signaling to merge in ahead of this one
+    match courtesy_response.response {
+        MergeResponse::OpenGap => {
+            // ease off until there's room for the merging car plus this driver's usual tail distance
+            let min_tail_distance =
+                CAR_SIZE.y * driver_temperament_tail_threshold(&agent.temperament);
+
+            if courtesy_response.gap < CAR_SIZE.y + min_tail_distance {
+                let speed = velocity.y;
+                velocity.y -= CAR_BRAKE_POWER * COURTESY_BRAKE_PCT;
+                velocity.y = f32::max(velocity.y, 0.);
+
+                Decision::new(
+                    DecisionBranch::OpenGap,
+                    vec![
+                        ("speed", speed),
+                        ("gap", courtesy_response.gap),
+                        ("min_tail_distance", min_tail_distance),
+                    ],
+                )
+            } else {
+                agent_accelerate_or_brake(agent, velocity, time)
+            }
         }
-
-        if bounding_box.intersects(&Aabb2d::new(
-            target_transform.translation.truncate(),
-            CAR_SIZE_HALF.truncate(),
-        )) {
-            return false;
+        MergeResponse::CloseGap => {
+            // speed up to sit alongside the merging car, still respecting whatever is directly ahead
+            // and the driver's own top speed
+            agent_accelerate_or_brake(agent, velocity, time)
         }
+        MergeResponse::Ignore => agent_accelerate_or_brake(agent, velocity, time),
     }
+}
 
-    true
+fn has_obstacle_in_range(agent: &DriverAgent) -> bool {
+    agent.collision_information.front_distance > -1.
 }
 
-fn brake_for_front(agent: &DriverAgent, velocity: &mut Velocity, time: &Res<Time>) {
+fn brake_for_front(agent: &DriverAgent, velocity: &mut Velocity, time: &Time) -> Decision {
     let distance = agent.collision_information.front_distance;
     let brake_distance_threshold =
         CAR_SIGHT_DISTANCE * driver_temperament_brake_threshold(&agent.temperament);
@@ -345,9 +788,15 @@ fn brake_for_front(agent: &DriverAgent, velocity: &mut Velocity, time: &Res<Time
     let previous_distance = agent.collision_information.last_front_distance;
     let distance_difference = distance - previous_distance; // negative means approaching vehicle ahead
 
-    info!("distance={distance}");
-
     let mut velocity_change = CAR_GAS_POWER;
+    let mut decision = Decision::new(
+        DecisionBranch::OutsideBrakeDistance,
+        vec![
+            ("speed", velocity.y),
+            ("distance", distance),
+            ("brake_distance", brake_distance_threshold),
+        ],
+    );
 
     // println!("if distance={distance} <= brake_distance_threshold={brake_distance_threshold}");
 
@@ -382,18 +831,29 @@ fn brake_for_front(agent: &DriverAgent, velocity: &mut Velocity, time: &Res<Time
 
             velocity_change = -brake_power;
 
-            info!("BRAKE_MIN tail_threshold_pct={tail_threshold_pct}, min_tail_distance={min_tail_distance}, distance={distance}, \
-            min_tail_distance={min_tail_distance}, power_percentage={power_percentage}, brake_power={brake_power}, distance_difference={distance_difference}");
+            decision.branch = DecisionBranch::BrakeWithinTail;
+            decision.inputs.extend([
+                ("min_tail_distance", min_tail_distance),
+                ("distance_difference", distance_difference),
+                ("brake_power", brake_power),
+            ]);
 
             // velocity_change = -CAR_BRAKE_POWER;
         } else {
-            // within braking distance, but outside tail distance; here, use the change in distance to approximate the relative speed
-            // of the vehicle ahead; for example, a distance difference of zero implies we are perfectly tailing the car ahead
+            // within braking distance, but outside tail distance; here, use the relative speed of the vehicle ahead as the
+            // driver perceives it, falling back to the change in distance; for example, a relative speed of zero implies we
+            // are perfectly tailing the car ahead
             // if we're still outside of the min tail distance, we can accelerate, so long as we aren't approaching at a reckless speed
-            let relative_speed = distance_difference / time.delta_seconds();
+            let relative_speed = match agent.collision_information.vehicles_ahead.first() {
+                Some(leader) => leader.speed - velocity.y,
+                None => distance_difference / time.delta_seconds(),
+            };
             let relative_speed_threshold_for_accel = 5. * CAR_GAS_POWER;
 
-            info!("relative_speed={relative_speed} speed={}", velocity.y);
+            decision.inputs.extend([
+                ("min_tail_distance", min_tail_distance),
+                ("relative_speed", relative_speed),
+            ]);
 
             let adjusted_distance = distance - min_tail_distance;
             let adjusted_threshold = f32::max(brake_distance_threshold - min_tail_distance, 0.); // max for non-zero div
@@ -410,8 +870,11 @@ fn brake_for_front(agent: &DriverAgent, velocity: &mut Velocity, time: &Res<Time
                 velocity_change = gas_power;
 
                 // }
-                info!("ACCEL_REL tail_threshold_pct={tail_threshold_pct}, min_tail_distance={min_tail_distance}, adjusted_distance={adjusted_distance}, \
-                       adjusted_threshold={adjusted_threshold}, power_percentage={power_percentage}, gas_power={gas_power}, distance_difference={distance_difference}");
+                decision.branch = DecisionBranch::AccelerateRelative;
+                decision.inputs.extend([
+                    ("power_percentage", power_percentage),
+                    ("gas_power", gas_power),
+                ]);
 
             // if distance_difference > 0. {
             //     // within brake threshold, but the car ahead is pulling away; can accelerate here
@@ -425,8 +888,11 @@ fn brake_for_front(agent: &DriverAgent, velocity: &mut Velocity, time: &Res<Time
 
                 velocity_change = -brake_power;
 
-                info!("BRAKE_REL tail_threshold_pct={tail_threshold_pct}, min_tail_distance={min_tail_distance}, adjusted_distance={adjusted_distance}, \
-                       adjusted_threshold={adjusted_threshold}, brake_percentage={brake_percentage}, brake_power={brake_power}, distance_difference={distance_difference}, speed_percentage={speed_percentage}");
+                decision.branch = DecisionBranch::BrakeRelative;
+                decision.inputs.extend([
+                    ("speed_percentage", speed_percentage),
+                    ("brake_power", brake_power),
+                ]);
 
                 // // every agent will always brake when within its minimum tail distance
                 // if distance < min_tail_distance {
@@ -462,45 +928,24 @@ fn brake_for_front(agent: &DriverAgent, velocity: &mut Velocity, time: &Res<Time
     //     "distance={} previous_distance={} speed change={} new velocity={}",
     //     distance, previous_distance, velocity_change, velocity.y
     // );
-}
-
-fn agent_normal_behavior(agent: &mut DriverAgent, velocity: &mut Velocity, transform: &Transform) {
-    // if velocity.y < SPEED_LIMIT {
-    //     velocity.y += CAR_GAS_POWER;
-    // }
 
-    let current_lane_idx = lane_idx_from_screen_pos(&transform.translation.truncate());
-
-    agent.driver_state = DriverState::ChangingLanes;
-
-    // println!(
-    //     "current_pos={} current_lane_idx{}",
-    //     transform.translation, current_lane_idx
-    // );
+    decision
 }
 
 pub fn collision_system(
-    mut collider_query: Query<
-        (
-            Entity,
-            &Transform,
-            &mut Handle<ColorMaterial>,
-            &mut DriverAgent,
-            &mut Velocity,
-        ),
-        With<Car>,
-    >,
-    mut materials: ResMut<Assets<ColorMaterial>>, // for changing car color on collision
+    mut collider_query: Query<(Entity, &Transform, &mut DriverAgent, &mut Velocity), With<Car>>,
+    car_following_config: Res<CarFollowingConfig>,
+    noise_config: Res<PerceptionNoiseConfig>,
 ) {
-    let mut add_intersections: HashMap<Entity, f32> = HashMap::new();
+    let mut add_intersections: HashMap<Entity, (Vec<VehicleAhead>, f32)> = HashMap::new();
     let mut clear_intersections: HashMap<Entity, f32> = HashMap::new();
 
-    for (entity_1, transform_1, _, _, _) in &collider_query {
-        let mut has_collision = false;
+    for (entity_1, transform_1, agent_1, velocity_1) in &collider_query {
+        let mut intersections: Vec<(VehicleAhead, f32)> = vec![];
 
         // TODO: don't calculate if agent already has a collision?
 
-        for (entity_2, transform_2, _, _, _) in &collider_query {
+        for (entity_2, transform_2, _, velocity_2) in &collider_query {
             if entity_1 == entity_2 {
                 continue;
             }
@@ -517,61 +962,82 @@ pub fn collision_system(
                 transform_2,
             ) {
                 // println!("Got intersection at {:?}", intersection);
-
-                // guarantee the closest intersection in case there are multiple
-                match add_intersections.get_mut(&entity_1) {
-                    Some(value) => {
-                        if intersection < *value {
-                            *value = intersection;
-                        }
-                    }
-                    None => {
-                        // doesn't exist yet; insert
-                        add_intersections.insert(entity_1, intersection);
-                    }
-                }
-
-                has_collision = true;
-                // TODO: if we could guarantee we saw the closest car (sort by distance?), could break here
-                // to prevent further checks
+                intersections.push((
+                    VehicleAhead {
+                        entity: entity_2,
+                        distance: intersection,
+                        speed: velocity_2.y,
+                    },
+                    transform_2.scale.x,
+                ));
             }
         }
 
-        if !has_collision {
+        if intersections.is_empty() {
             clear_intersections.insert(entity_1, -1.);
+            continue;
         }
+
+        intersections.sort_by(|(a, _), (b, _)| a.distance.total_cmp(&b.distance));
+
+        // physical contact is measured, not judged
+        let true_front_distance = intersections[0].0.distance;
+
+        // a driver sees past vehicles no wider than its own car; anything wider blocks the view of
+        // whatever is in front of it
+        let mut vehicles_ahead = vec![];
+
+        for (mut vehicle_ahead, width) in intersections {
+            let relative_speed = vehicle_ahead.speed - velocity_1.y;
+            let perceived_relative_speed = perceive_relative_speed(
+                agent_1,
+                relative_speed,
+                vehicle_ahead.distance,
+                &noise_config,
+            );
+
+            vehicle_ahead.speed = velocity_1.y + perceived_relative_speed;
+            vehicle_ahead.distance =
+                perceive_distance(agent_1, vehicle_ahead.distance, &noise_config);
+            vehicles_ahead.push(vehicle_ahead);
+
+            if width > transform_1.scale.x
+                || vehicles_ahead.len() >= car_following_config.anticipation_depth
+            {
+                break;
+            }
+        }
+
+        add_intersections.insert(entity_1, (vehicles_ahead, true_front_distance));
     }
 
-    for (entity_id, intersection_distance) in add_intersections {
+    for (entity_id, (vehicles_ahead, true_front_distance)) in add_intersections {
         if let Ok(mut entity) = collider_query.get_mut(entity_id) {
-            let handle: &Handle<ColorMaterial> = &entity.2;
-            if let Some(material) = materials.get_mut(handle) {
-                material.color = Color::RED;
-            }
+            // the closest intersection is the car directly in front
+            let intersection_distance = vehicles_ahead[0].distance;
 
             // set previous then current
-            entity.3.collision_information.last_front_distance =
-                entity.3.collision_information.front_distance;
+            entity.2.collision_information.last_front_distance =
+                entity.2.collision_information.front_distance;
 
             // intersection returns distance to the *front* of the next car; offset to give distance to rear
-            entity.3.collision_information.front_distance = intersection_distance;
+            entity.2.collision_information.front_distance = intersection_distance;
+            entity.2.collision_information.vehicles_ahead = vehicles_ahead;
+            entity.2.collision_information.in_contact = true_front_distance <= 0.;
 
             // made contact with an object: come to a full stop
-            if intersection_distance <= 0. {
-                entity.4.x = 0.;
-                entity.4.y = 0.;
+            if true_front_distance <= 0. {
+                entity.3.x = 0.;
+                entity.3.y = 0.;
             }
         }
     }
 
     for (entity_id, _) in clear_intersections {
         if let Ok(mut entity) = collider_query.get_mut(entity_id) {
-            let handle: &Handle<ColorMaterial> = &entity.2;
-            if let Some(material) = materials.get_mut(handle) {
-                material.color = CAR_COLOR;
-            }
-
-            entity.3.collision_information.front_distance = -1.;
+            entity.2.collision_information.front_distance = -1.;
+            entity.2.collision_information.vehicles_ahead.clear();
+            entity.2.collision_information.in_contact = false;
         }
     }
 }
@@ -587,3 +1053,225 @@ pub fn check_raycast_intersection(
 
     raycast.aabb_intersection_at(&aabb2d)
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::sprite::Mesh2dHandle;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn agent(
+        lawfulness: DriverLawfulness,
+        temperament: DriverTemperament,
+        front_distance: f32,
+        last_front_distance: f32,
+    ) -> DriverAgent {
+        let mut agent = CarBundle::new_with_behavior(
+            Vec3::ZERO,
+            Mesh2dHandle::default(),
+            Handle::default(),
+            lawfulness,
+            temperament,
+            DriverPatience::Normal,
+            DriverAttentiveness::Normal,
+        )
+        .driver_agent;
+        agent.collision_information.front_distance = front_distance;
+        agent.collision_information.last_front_distance = last_front_distance;
+        agent
+    }
+
+    fn one_tick() -> Time {
+        let mut time = Time::default();
+        time.advance_by(Duration::from_secs_f32(1. / 64.));
+        time
+    }
+
+    // the slowest a calm, normally patient driver will put up with before wanting to pass
+    fn pass_threshold() -> f32 {
+        SPEED_LIMIT
+            * driver_temperament_top_speed_pct(&DriverTemperament::Calm)
+            * driver_patience_min_speed_pct(&DriverPatience::Normal)
+    }
+
+    fn brake_distance() -> f32 {
+        CAR_SIGHT_DISTANCE * driver_temperament_brake_threshold(&DriverTemperament::Calm)
+    }
+
+    fn min_tail_distance() -> f32 {
+        CAR_SIZE.y * driver_temperament_tail_threshold(&DriverTemperament::Calm)
+    }
+
+    #[test]
+    fn orderly_driver_on_a_clear_road_moves_right() {
+        let agent = agent(DriverLawfulness::Orderly, DriverTemperament::Calm, -1., -1.);
+        let velocity = Velocity(Vec2::new(0., 0.));
+
+        for lane in 0..NUM_LANES - 1 {
+            assert_eq!(
+                get_lane_change_direction(&agent, &velocity, lane),
+                LaneChangeDirection::Right
+            );
+        }
+    }
+
+    #[test]
+    fn orderly_driver_in_the_right_lane_stays() {
+        let agent = agent(DriverLawfulness::Orderly, DriverTemperament::Calm, -1., -1.);
+        let velocity = Velocity(Vec2::new(0., 0.));
+
+        assert_eq!(
+            get_lane_change_direction(&agent, &velocity, NUM_LANES - 1),
+            LaneChangeDirection::None
+        );
+    }
+
+    #[test]
+    fn chaotic_driver_on_a_clear_road_stays() {
+        let agent = agent(DriverLawfulness::Chaotic, DriverTemperament::Calm, -1., -1.);
+        let velocity = Velocity(Vec2::new(0., 0.));
+
+        assert_eq!(
+            get_lane_change_direction(&agent, &velocity, 0),
+            LaneChangeDirection::None
+        );
+    }
+
+    #[test]
+    fn driver_slowed_behind_a_car_moves_left_to_pass() {
+        let velocity = Velocity(Vec2::new(0., pass_threshold() - 1.));
+
+        for lawfulness in [DriverLawfulness::Orderly, DriverLawfulness::Chaotic] {
+            let agent = agent(lawfulness, DriverTemperament::Calm, 50., 50.);
+            for lane in 0..NUM_LANES {
+                assert_eq!(
+                    get_lane_change_direction(&agent, &velocity, lane),
+                    LaneChangeDirection::Left
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn driver_keeping_pace_behind_a_car_does_not_pass() {
+        let velocity = Velocity(Vec2::new(0., pass_threshold()));
+
+        let orderly = agent(DriverLawfulness::Orderly, DriverTemperament::Calm, 50., 50.);
+        assert_eq!(
+            get_lane_change_direction(&orderly, &velocity, 0),
+            LaneChangeDirection::Right
+        );
+
+        let chaotic = agent(DriverLawfulness::Chaotic, DriverTemperament::Calm, 50., 50.);
+        assert_eq!(
+            get_lane_change_direction(&chaotic, &velocity, 0),
+            LaneChangeDirection::None
+        );
+    }
+
+    #[test]
+    fn brake_for_front_accelerates_outside_brake_distance() {
+        let distance = brake_distance() + 1.;
+        let agent = agent(
+            DriverLawfulness::Orderly,
+            DriverTemperament::Calm,
+            distance,
+            distance,
+        );
+        let mut velocity = Velocity(Vec2::new(0., 100.));
+
+        let decision = brake_for_front(&agent, &mut velocity, &one_tick());
+
+        assert_eq!(decision.branch, DecisionBranch::OutsideBrakeDistance);
+        assert_eq!(velocity.y, 100. + CAR_GAS_POWER);
+    }
+
+    #[test]
+    fn brake_for_front_brakes_harder_deeper_within_tail_distance() {
+        let brake_at = |distance: f32| {
+            let agent = agent(
+                DriverLawfulness::Orderly,
+                DriverTemperament::Calm,
+                distance,
+                distance,
+            );
+            let mut velocity = Velocity(Vec2::new(0., 100.));
+            let decision = brake_for_front(&agent, &mut velocity, &one_tick());
+            assert_eq!(decision.branch, DecisionBranch::BrakeWithinTail);
+            100. - velocity.y
+        };
+
+        let near = brake_at(min_tail_distance() * 0.25);
+        let far = brake_at(min_tail_distance() * 0.75);
+        assert!(near > far && far > 0., "near {near} far {far}");
+        assert!(near <= CAR_BRAKE_POWER);
+    }
+
+    #[test]
+    fn brake_for_front_follows_a_car_pulling_away() {
+        let distance = (min_tail_distance() + brake_distance()) / 2.;
+        let agent = agent(
+            DriverLawfulness::Orderly,
+            DriverTemperament::Calm,
+            distance,
+            distance - 1.,
+        );
+        let mut velocity = Velocity(Vec2::new(0., 100.));
+
+        let decision = brake_for_front(&agent, &mut velocity, &one_tick());
+
+        assert_eq!(decision.branch, DecisionBranch::AccelerateRelative);
+        assert!(velocity.y > 100. && velocity.y <= 100. + CAR_GAS_POWER);
+    }
+
+    #[test]
+    fn brake_for_front_brakes_when_closing_quickly() {
+        let distance = (min_tail_distance() + brake_distance()) / 2.;
+        let agent = agent(
+            DriverLawfulness::Orderly,
+            DriverTemperament::Calm,
+            distance,
+            distance + 10.,
+        );
+        let mut velocity = Velocity(Vec2::new(0., 100.));
+
+        let decision = brake_for_front(&agent, &mut velocity, &one_tick());
+
+        assert_eq!(decision.branch, DecisionBranch::BrakeRelative);
+        assert_eq!(velocity.y, 100. - CAR_BRAKE_POWER);
+    }
+
+    #[test]
+    fn brake_for_front_reads_relative_speed_off_the_leader() {
+        // the gap shrank since last tick, but the driver can see the car ahead pulling away
+        let distance = (min_tail_distance() + brake_distance()) / 2.;
+        let mut agent = agent(
+            DriverLawfulness::Orderly,
+            DriverTemperament::Calm,
+            distance,
+            distance + 10.,
+        );
+        agent.collision_information.vehicles_ahead = vec![VehicleAhead {
+            entity: Entity::PLACEHOLDER,
+            distance,
+            speed: 120.,
+        }];
+        let mut velocity = Velocity(Vec2::new(0., 100.));
+
+        let decision = brake_for_front(&agent, &mut velocity, &one_tick());
+
+        assert_eq!(decision.branch, DecisionBranch::AccelerateRelative);
+        assert_eq!(velocity.y, 100. + CAR_GAS_POWER);
+    }
+
+    #[test]
+    fn brake_for_front_never_reverses() {
+        let agent = agent(DriverLawfulness::Orderly, DriverTemperament::Calm, 0., 0.);
+        let mut velocity = Velocity(Vec2::new(0., 1.));
+
+        brake_for_front(&agent, &mut velocity, &one_tick());
+
+        assert_eq!(velocity.y, 0.);
+    }
+}
@@ -0,0 +1,170 @@
+use bevy::prelude::*;
+use bevy_mod_picking::prelude::*;
+use bevy_picking_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::components::*;
+use crate::constants::*;
+use crate::events::*;
+use crate::resources::*;
+use crate::systems::select_only;
+use crate::util::*;
+
+// the kinds of condition offered when adding a breakpoint, each with its default parameter
+const NEW_CONDITIONS: [BreakCondition; 5] = [
+    BreakCondition::Collision,
+    BreakCondition::LaneChange(None),
+    BreakCondition::GapBelow(BREAKPOINT_DEFAULT_GAP),
+    BreakCondition::DecelerationAbove(BREAKPOINT_DEFAULT_DECELERATION),
+    BreakCondition::EntersState(DriverState::Crashed),
+];
+
+// the breakpoint being put together in the "add" row, kept between frames
+#[derive(Default)]
+pub struct NewBreakpoint {
+    condition: Option<BreakCondition>,
+}
+
+pub fn breakpoints_ui(
+    mut egui_contexts: EguiContexts,
+    debug_state: Res<State<DebugState>>,
+    mut breakpoints: ResMut<Breakpoints>,
+    mut new_breakpoint: Local<NewBreakpoint>,
+    selected_query: Query<Entity, With<SelectedEntity>>,
+    mut selection_query: Query<(Entity, &mut PickSelection, Has<SelectedEntity>), With<Car>>,
+    mut select_writer: EventWriter<SelectEntityEvent>,
+    mut deselect_writer: EventWriter<DeselectEntityEvent>,
+) {
+    if debug_state.get() != &DebugState::Enabled {
+        return;
+    }
+
+    let selected = selected_query.iter().min();
+    let mut to_select = None;
+
+    egui::Window::new("Breakpoints")
+        .default_width(260.)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            let condition = new_breakpoint
+                .condition
+                .get_or_insert(BreakCondition::Collision);
+
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source("new_breakpoint_kind")
+                    .selected_text(condition_kind(condition))
+                    .show_ui(ui, |ui| {
+                        for option in NEW_CONDITIONS {
+                            let label = condition_kind(&option);
+                            let is_current = condition_kind(condition) == label;
+                            if ui.selectable_label(is_current, label).clicked() && !is_current {
+                                *condition = option;
+                            }
+                        }
+                    });
+
+                match condition {
+                    BreakCondition::Collision => {}
+                    BreakCondition::LaneChange(target) => {
+                        let mut only_selected = target.is_some();
+                        ui.add_enabled_ui(selected.is_some() || only_selected, |ui| {
+                            ui.checkbox(&mut only_selected, "selected car only");
+                        });
+                        *target = if only_selected {
+                            target.or(selected)
+                        } else {
+                            None
+                        };
+                    }
+                    BreakCondition::GapBelow(gap) => {
+                        ui.add(egui::DragValue::new(gap).clamp_range(0.0..=CAR_SIGHT_DISTANCE));
+                    }
+                    BreakCondition::DecelerationAbove(deceleration) => {
+                        ui.add(egui::DragValue::new(deceleration).clamp_range(0.0..=f32::MAX));
+                    }
+                    BreakCondition::EntersState(state) => {
+                        egui::ComboBox::from_id_source("new_breakpoint_state")
+                            .selected_text(format!("{:?}", state))
+                            .show_ui(ui, |ui| {
+                                for option in DRIVER_STATES {
+                                    ui.selectable_value(state, option, format!("{:?}", option));
+                                }
+                            });
+                    }
+                }
+
+                if ui.button("Add").clicked() {
+                    breakpoints.breakpoints.push(Breakpoint {
+                        condition: condition.clone(),
+                        enabled: true,
+                        active: Default::default(),
+                    });
+                }
+            });
+
+            ui.separator();
+
+            let mut removed = None;
+            for (idx, breakpoint) in breakpoints.breakpoints.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut breakpoint.enabled, breakpoint.condition.describe());
+                    if ui.small_button("✖").clicked() {
+                        removed = Some(idx);
+                    }
+                });
+            }
+            if let Some(idx) = removed {
+                breakpoints.breakpoints.remove(idx);
+            }
+
+            if breakpoints.breakpoints.is_empty() {
+                ui.weak("no breakpoints");
+            }
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(format!("Hits ({})", breakpoints.hits.len()));
+                if ui.small_button("Clear").clicked() {
+                    breakpoints.hits.clear();
+                }
+            });
+
+            egui::ScrollArea::vertical()
+                .max_height(200.)
+                .show(ui, |ui| {
+                    // newest first
+                    for hit in breakpoints.hits.iter().rev() {
+                        let label = format!(
+                            "{:>7.2}s  {}  {:?}",
+                            hit.time,
+                            hit.condition.describe(),
+                            hit.entities
+                        );
+                        if ui
+                            .selectable_label(false, egui::RichText::new(label).monospace())
+                            .on_hover_text("select the cars involved")
+                            .clicked()
+                        {
+                            to_select = Some(hit.entities.clone());
+                        }
+                    }
+                });
+        });
+
+    if let Some(entities) = to_select {
+        select_only(
+            &entities,
+            &mut selection_query,
+            &mut select_writer,
+            &mut deselect_writer,
+        );
+    }
+}
+
+fn condition_kind(condition: &BreakCondition) -> &'static str {
+    match condition {
+        BreakCondition::Collision => "Collision",
+        BreakCondition::LaneChange(_) => "Lane change",
+        BreakCondition::GapBelow(_) => "Gap below",
+        BreakCondition::DecelerationAbove(_) => "Deceleration above",
+        BreakCondition::EntersState(_) => "Enters state",
+    }
+}
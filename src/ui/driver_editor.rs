@@ -0,0 +1,159 @@
+use bevy::prelude::*;
+use bevy_picking_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::components::*;
+use crate::events::*;
+use crate::util::*;
+
+const LAWFULNESSES: [DriverLawfulness; 2] = [DriverLawfulness::Chaotic, DriverLawfulness::Orderly];
+
+const TEMPERAMENTS: [DriverTemperament; 4] = [
+    DriverTemperament::Psychotic,
+    DriverTemperament::Aggressive,
+    DriverTemperament::Calm,
+    DriverTemperament::Passive,
+];
+
+const PATIENCES: [DriverPatience; 4] = [
+    DriverPatience::Enlightened,
+    DriverPatience::Patient,
+    DriverPatience::Normal,
+    DriverPatience::Wild,
+];
+
+const PROFILES: [DriverProfile; 5] = [
+    DriverProfile::Commuter,
+    DriverProfile::SundayDriver,
+    DriverProfile::Speeder,
+    DriverProfile::RoadRager,
+    DriverProfile::Distracted,
+];
+
+pub fn driver_editor_ui(
+    mut egui_contexts: EguiContexts,
+    debug_state: Res<State<DebugState>>,
+    query: Query<(&DriverAgent, Option<&Frozen>), With<SelectedEntity>>,
+    mut modify_writer: EventWriter<ModifySelectedDriverAgentEvent>,
+    mut action_writer: EventWriter<SelectionActionEvent>,
+) {
+    if debug_state.get() != &DebugState::Enabled {
+        return;
+    }
+
+    egui::Window::new("Driver Editor").show(egui_contexts.ctx_mut(), |ui| {
+        let agents: Vec<&DriverAgent> = query.iter().map(|(agent, _)| agent).collect();
+
+        if agents.is_empty() {
+            ui.heading("Please select a car!");
+            ui.label("Shift-click or shift-drag to select several.");
+            return;
+        }
+
+        let frozen_count = query.iter().filter(|(_, frozen)| frozen.is_some()).count();
+        ui.heading(format!(
+            "{} cars selected ({} frozen)",
+            agents.len(),
+            frozen_count
+        ));
+
+        egui::Grid::new("driver_editor_axes").show(ui, |ui| {
+            if let Some(lawfulness) = pick_common(
+                ui,
+                "Lawfulness",
+                &LAWFULNESSES,
+                agents.iter().map(|agent| &agent.lawfulness),
+            ) {
+                modify_writer.send(ModifySelectedDriverAgentEvent(DriverAgentEdit::Lawfulness(
+                    lawfulness,
+                )));
+            }
+            ui.end_row();
+
+            if let Some(temperament) = pick_common(
+                ui,
+                "Temperament",
+                &TEMPERAMENTS,
+                agents.iter().map(|agent| &agent.temperament),
+            ) {
+                modify_writer.send(ModifySelectedDriverAgentEvent(
+                    DriverAgentEdit::Temperament(temperament),
+                ));
+            }
+            ui.end_row();
+
+            if let Some(patience) = pick_common(
+                ui,
+                "Patience",
+                &PATIENCES,
+                agents.iter().map(|agent| &agent.patience),
+            ) {
+                modify_writer.send(ModifySelectedDriverAgentEvent(DriverAgentEdit::Patience(
+                    patience,
+                )));
+            }
+            ui.end_row();
+        });
+
+        ui.separator();
+        ui.label("Profiles");
+        ui.horizontal_wrapped(|ui| {
+            for profile in PROFILES {
+                if ui.button(format!("{:?}", profile)).clicked() {
+                    modify_writer.send(ModifySelectedDriverAgentEvent(DriverAgentEdit::Profile(
+                        profile,
+                    )));
+                }
+            }
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            for (label, action) in [
+                ("Duplicate", SelectionAction::Duplicate),
+                ("Freeze", SelectionAction::Freeze),
+                ("Unfreeze", SelectionAction::Unfreeze),
+                ("Delete", SelectionAction::Delete),
+            ] {
+                if ui.button(label).clicked() {
+                    action_writer.send(SelectionActionEvent(action));
+                }
+            }
+        });
+    });
+}
+
+fn pick_common<'a, T: std::fmt::Debug + Clone + PartialEq + 'a>(
+    ui: &mut egui::Ui,
+    label: &str,
+    options: &[T],
+    mut values: impl Iterator<Item = &'a T>,
+) -> Option<T> {
+    // a combo box over the selection's shared value (or "mixed" when the cars disagree);
+    // returns the newly picked value, if any
+    let first = values.next().cloned();
+    let common = first.filter(|first| values.all(|value| value == first));
+
+    let selected_text = common
+        .as_ref()
+        .map_or("mixed".to_string(), |common| format!("{:?}", common));
+
+    let mut picked = None;
+
+    ui.label(label);
+    egui::ComboBox::from_id_source(label)
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            for option in options {
+                let is_current = common.as_ref() == Some(option);
+                if ui
+                    .selectable_label(is_current, format!("{:?}", option))
+                    .clicked()
+                    && !is_current
+                {
+                    picked = Some(option.clone());
+                }
+            }
+        });
+
+    picked
+}
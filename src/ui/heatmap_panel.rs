@@ -0,0 +1,44 @@
+use bevy::prelude::*;
+use bevy_picking_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::constants::*;
+use crate::resources::*;
+use crate::util::*;
+
+const HEATMAP_METRICS: [HeatmapMetric; 3] = [
+    HeatmapMetric::Speed,
+    HeatmapMetric::Density,
+    HeatmapMetric::Flow,
+];
+
+pub fn heatmap_ui(mut egui_contexts: EguiContexts, mut heatmap: ResMut<Heatmap>) {
+    if !heatmap.visible {
+        return;
+    }
+
+    egui::Window::new("Heatmap")
+        .resizable(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                for metric in HEATMAP_METRICS {
+                    ui.selectable_value(&mut heatmap.metric, metric, format!("{:?}", metric));
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Smoothing (s)");
+                ui.add(egui::Slider::new(
+                    &mut heatmap.smoothing_window,
+                    0.5..=METRICS_HISTORY / 10.,
+                ));
+            });
+
+            ui.weak(match heatmap.metric {
+                HeatmapMetric::Speed => "red: slow, green: at the speed limit".to_string(),
+                HeatmapMetric::Density => {
+                    format!("full red: {HEATMAP_MAX_DENSITY} cars per 1000 units")
+                }
+                HeatmapMetric::Flow => format!("full blue: {HEATMAP_MAX_FLOW} cars per minute"),
+            });
+        });
+}
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+use bevy_picking_egui::bevy_egui::{
+    egui::{self, ScrollArea},
+    EguiContexts,
+};
+
+use crate::events::*;
+use crate::history::*;
+use crate::util::*;
+
+pub fn history_ui(
+    mut egui_contexts: EguiContexts,
+    debug_state: Res<State<DebugState>>,
+    history: Res<History>,
+    mut history_writer: EventWriter<HistoryRequestEvent>,
+) {
+    if debug_state.get() != &DebugState::Enabled {
+        return;
+    }
+
+    egui::Window::new("History").show(egui_contexts.ctx_mut(), |ui| {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!history.undo_stack.is_empty(), egui::Button::new("Undo"))
+                .clicked()
+            {
+                history_writer.send(HistoryRequestEvent(HistoryRequest::Undo));
+            }
+            if ui
+                .add_enabled(!history.redo_stack.is_empty(), egui::Button::new("Redo"))
+                .clicked()
+            {
+                history_writer.send(HistoryRequestEvent(HistoryRequest::Redo));
+            }
+        });
+
+        ui.separator();
+
+        // applied edits oldest first, then the undone ones greyed out; clicking an entry jumps to
+        // just after it
+        ScrollArea::vertical().max_height(300.).show(ui, |ui| {
+            let undo_len = history.undo_stack.len();
+
+            if ui.selectable_label(undo_len == 0, "(start)").clicked() {
+                history_writer.send(HistoryRequestEvent(HistoryRequest::JumpTo(0)));
+            }
+
+            for (i, edit) in history.undo_stack.iter().enumerate() {
+                if ui
+                    .selectable_label(i + 1 == undo_len, &edit.label)
+                    .clicked()
+                {
+                    history_writer.send(HistoryRequestEvent(HistoryRequest::JumpTo(i + 1)));
+                }
+            }
+
+            for (i, edit) in history.redo_stack.iter().rev().enumerate() {
+                let label = egui::RichText::new(&edit.label).weak();
+                if ui.selectable_label(false, label).clicked() {
+                    history_writer.send(HistoryRequestEvent(HistoryRequest::JumpTo(
+                        undo_len + i + 1,
+                    )));
+                }
+            }
+        });
+    });
+}
@@ -0,0 +1,331 @@
+use bevy::{
+    prelude::*,
+    reflect::{DynamicEnum, DynamicVariant, Enum, ReflectMut, ReflectRef, TypeInfo, VariantInfo},
+    utils::get_short_name,
+    window::PrimaryWindow,
+};
+use bevy_picking_egui::bevy_egui::{
+    egui::{self, ScrollArea},
+    EguiContext,
+};
+
+use crate::components::*;
+use crate::constants::*;
+use crate::events::*;
+use crate::resources::*;
+use crate::util::*;
+
+pub fn inspector_ui(world: &mut World) {
+    // debug window shows when paused and debug mode is on
+    if world.resource::<State<PauseState>>().get() != &PauseState::Paused
+        || world.resource::<State<DebugState>>().get() != &DebugState::Enabled
+    {
+        return;
+    }
+
+    // the system needs the whole world to reflect over arbitrary components, so the egui
+    // context is cloned out of it rather than borrowed through `EguiContexts`
+    let Ok(egui_context) = world
+        .query_filtered::<&EguiContext, With<PrimaryWindow>>()
+        .get_single(world)
+    else {
+        return;
+    };
+    let mut egui_context = egui_context.clone();
+
+    // with several cars selected, the one with the lowest index is inspected
+    let mut selected: Vec<Entity> = world
+        .query_filtered::<Entity, With<SelectedEntity>>()
+        .iter(world)
+        .collect();
+    selected.sort();
+
+    let mut edits = vec![];
+
+    egui::Window::new("Entity Inspector").show(egui_context.get_mut(), |ui| {
+        ScrollArea::both().auto_shrink([false; 2]).show(ui, |ui| {
+            let Some(&entity) = selected.first() else {
+                ui.heading("Please select an entity!");
+                return;
+            };
+
+            ui.heading(format!("You have selected entity {:?}!", entity));
+            if selected.len() > 1 {
+                ui.label(format!("({} others also selected)", selected.len() - 1));
+            }
+            entity_ui(ui, world, entity, &mut edits);
+
+            let mut decision_trace = world.resource_mut::<DecisionTrace>();
+            decision_trace_ui(ui, &mut decision_trace, entity);
+        });
+    });
+
+    // edits are applied by `modify_component_listener`, not here, so every change to an
+    // entity goes through an event
+    for edit in edits {
+        world.send_event(edit);
+    }
+}
+
+fn entity_ui(
+    ui: &mut egui::Ui,
+    world: &World,
+    entity: Entity,
+    edits: &mut Vec<ModifyComponentEvent>,
+) {
+    let type_registry = world.resource::<AppTypeRegistry>().read();
+
+    let mut components: Vec<_> = world
+        .inspect_entity(entity)
+        .into_iter()
+        .map(|component_info| {
+            (
+                get_short_name(component_info.name()),
+                component_info.type_id(),
+            )
+        })
+        .collect();
+    components.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    for (name, type_id) in components {
+        let reflect_component = type_id
+            .and_then(|type_id| type_registry.get(type_id))
+            .and_then(|registration| registration.data::<ReflectComponent>());
+
+        // components that aren't registered for reflection can only be listed
+        let Some(component) = reflect_component
+            .and_then(|reflect_component| reflect_component.reflect(world.entity(entity)))
+        else {
+            ui.weak(name);
+            continue;
+        };
+
+        let mut value = component.clone_value();
+
+        let changed = egui::CollapsingHeader::new(&name)
+            .show(ui, |ui| reflect_ui(ui, value.as_mut()))
+            .body_returned
+            .unwrap_or(false);
+
+        if changed {
+            edits.push(ModifyComponentEvent {
+                entity,
+                component: value,
+            });
+        }
+    }
+}
+
+fn decision_trace_ui(ui: &mut egui::Ui, decision_trace: &mut DecisionTrace, entity: Entity) {
+    egui::CollapsingHeader::new("Decision trace")
+        .default_open(true)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut decision_trace.enabled, "Record");
+                ui.checkbox(&mut decision_trace.filter.car_following, "Car following");
+                ui.checkbox(&mut decision_trace.filter.lane_change, "Lane changes");
+            });
+
+            ui.horizontal(|ui| {
+                let filter = &mut decision_trace.filter;
+                egui::ComboBox::from_label("Branch")
+                    .selected_text(
+                        filter
+                            .branch
+                            .map_or("Any".to_string(), |branch| format!("{:?}", branch)),
+                    )
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut filter.branch, None, "Any");
+                        for branch in DecisionBranch::ALL {
+                            ui.selectable_value(
+                                &mut filter.branch,
+                                Some(branch),
+                                format!("{:?}", branch),
+                            );
+                        }
+                    });
+
+                // exports what the filter lets through, for this car only
+                if ui.button("Export").clicked() {
+                    match std::fs::write(DECISION_TRACE_PATH, decision_trace.to_csv(entity)) {
+                        Ok(()) => info!(
+                            "exported decisions of {:?} to {DECISION_TRACE_PATH}",
+                            entity
+                        ),
+                        Err(error) => {
+                            error!("couldn't export decisions to {DECISION_TRACE_PATH}: {error}")
+                        }
+                    }
+                }
+            });
+
+            let Some(records) = decision_trace.vehicles.get(&entity) else {
+                ui.weak("no decisions recorded");
+                return;
+            };
+
+            // newest first
+            let shown = records
+                .iter()
+                .rev()
+                .filter(|record| decision_trace.filter.matches(record))
+                .take(DECISION_TRACE_SHOWN);
+
+            for record in shown {
+                let acceleration = record.acceleration.map_or(String::new(), |acceleration| {
+                    format!(" {acceleration:+.0}/s")
+                });
+                ui.label(format!(
+                    "{:.2}s {:?}{acceleration}",
+                    record.time, record.decision.branch
+                ));
+
+                let inputs: Vec<String> = record
+                    .decision
+                    .inputs
+                    .iter()
+                    .map(|(name, value)| format!("{name}={value:.2}"))
+                    .collect();
+                ui.weak(inputs.join(", "));
+            }
+        });
+}
+
+fn reflect_ui(ui: &mut egui::Ui, value: &mut dyn Reflect) -> bool {
+    // draws an editor for any reflected value, returning whether it was changed
+    let mut changed = false;
+
+    match value.reflect_mut() {
+        ReflectMut::Struct(value) => {
+            for i in 0..value.field_len() {
+                let name = value.name_at(i).unwrap_or_default().to_string();
+                if let Some(field) = value.field_at_mut(i) {
+                    changed |= field_ui(ui, &name, field);
+                }
+            }
+        }
+        ReflectMut::TupleStruct(value) => {
+            for i in 0..value.field_len() {
+                if let Some(field) = value.field_mut(i) {
+                    changed |= field_ui(ui, &i.to_string(), field);
+                }
+            }
+        }
+        ReflectMut::Tuple(value) => {
+            for i in 0..value.field_len() {
+                if let Some(field) = value.field_mut(i) {
+                    changed |= field_ui(ui, &i.to_string(), field);
+                }
+            }
+        }
+        ReflectMut::List(value) => {
+            if value.is_empty() {
+                ui.weak("empty");
+            }
+            for i in 0..value.len() {
+                if let Some(item) = value.get_mut(i) {
+                    changed |= field_ui(ui, &format!("[{i}]"), item);
+                }
+            }
+        }
+        ReflectMut::Array(value) => {
+            for i in 0..value.len() {
+                if let Some(item) = value.get_mut(i) {
+                    changed |= field_ui(ui, &format!("[{i}]"), item);
+                }
+            }
+        }
+        ReflectMut::Map(value) => {
+            ui.label(format!("{} entries", value.len()));
+        }
+        ReflectMut::Enum(value) => {
+            changed |= enum_ui(ui, value);
+        }
+        ReflectMut::Value(value) => {
+            changed |= value_ui(ui, value);
+        }
+    }
+
+    changed
+}
+
+fn field_ui(ui: &mut egui::Ui, name: &str, field: &mut dyn Reflect) -> bool {
+    // leaves sit on one line next to their name; anything with fields of its own gets a section
+    match field.reflect_ref() {
+        ReflectRef::Value(_) | ReflectRef::Enum(_) => {
+            ui.horizontal(|ui| {
+                ui.label(name);
+                reflect_ui(ui, field)
+            })
+            .inner
+        }
+        _ => egui::CollapsingHeader::new(name)
+            .show(ui, |ui| reflect_ui(ui, field))
+            .body_returned
+            .unwrap_or(false),
+    }
+}
+
+fn enum_ui(ui: &mut egui::Ui, value: &mut dyn Enum) -> bool {
+    let Some(TypeInfo::Enum(enum_info)) = value.get_represented_type_info() else {
+        ui.label(value.variant_name().to_string());
+        return false;
+    };
+
+    // variants carrying data can't be picked from a list; edit the active one's fields instead
+    if !enum_info
+        .iter()
+        .all(|variant| matches!(variant, VariantInfo::Unit(_)))
+    {
+        ui.label(value.variant_name().to_string());
+
+        let mut changed = false;
+        for i in 0..value.field_len() {
+            let name = value.name_at(i).map_or(i.to_string(), str::to_string);
+            if let Some(field) = value.field_at_mut(i) {
+                changed |= field_ui(ui, &name, field);
+            }
+        }
+        return changed;
+    }
+
+    let current = value.variant_name().to_string();
+    let mut selected = current.clone();
+
+    egui::ComboBox::from_id_source(ui.next_auto_id())
+        .selected_text(&selected)
+        .show_ui(ui, |ui| {
+            for variant_name in enum_info.variant_names() {
+                ui.selectable_value(&mut selected, variant_name.to_string(), *variant_name);
+            }
+        });
+
+    if selected == current {
+        return false;
+    }
+
+    value.apply(&DynamicEnum::new(selected, DynamicVariant::Unit));
+    true
+}
+
+fn value_ui(ui: &mut egui::Ui, value: &mut dyn Reflect) -> bool {
+    if let Some(value) = value.downcast_mut::<f32>() {
+        ui.add(egui::DragValue::new(value).speed(0.1)).changed()
+    } else if let Some(value) = value.downcast_mut::<f64>() {
+        ui.add(egui::DragValue::new(value).speed(0.1)).changed()
+    } else if let Some(value) = value.downcast_mut::<i32>() {
+        ui.add(egui::DragValue::new(value)).changed()
+    } else if let Some(value) = value.downcast_mut::<u32>() {
+        ui.add(egui::DragValue::new(value)).changed()
+    } else if let Some(value) = value.downcast_mut::<usize>() {
+        ui.add(egui::DragValue::new(value)).changed()
+    } else if let Some(value) = value.downcast_mut::<bool>() {
+        ui.checkbox(value, "").changed()
+    } else if let Some(value) = value.downcast_mut::<String>() {
+        ui.text_edit_singleline(value).changed()
+    } else {
+        // anything else (entities, handles, ranges, ...) is read-only
+        ui.label(format!("{:?}", value));
+        false
+    }
+}
@@ -0,0 +1,70 @@
+use bevy::prelude::*;
+use bevy_mod_picking::prelude::*;
+use bevy_picking_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::components::*;
+use crate::events::*;
+use crate::resources::*;
+use crate::systems::select_only;
+use crate::util::*;
+
+pub fn invariants_ui(
+    mut egui_contexts: EguiContexts,
+    debug_state: Res<State<DebugState>>,
+    mut checks: ResMut<InvariantChecks>,
+    mut selection_query: Query<(Entity, &mut PickSelection, Has<SelectedEntity>), With<Car>>,
+    mut select_writer: EventWriter<SelectEntityEvent>,
+    mut deselect_writer: EventWriter<DeselectEntityEvent>,
+) {
+    if debug_state.get() != &DebugState::Enabled {
+        return;
+    }
+
+    let mut to_select = None;
+
+    egui::Window::new("Invariants")
+        .default_open(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut checks.enabled, "Check after every tick");
+
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "Violations ({}, {} holding now)",
+                    checks.total,
+                    checks.active.len()
+                ));
+                if ui.small_button("Clear").clicked() {
+                    checks.violations.clear();
+                    checks.total = 0;
+                }
+            });
+
+            egui::ScrollArea::vertical()
+                .max_height(200.)
+                .show(ui, |ui| {
+                    // newest first
+                    for violation in checks.violations.iter().rev() {
+                        let label = format!(
+                            "tick {:>6}  {:?}  {:?}",
+                            violation.tick, violation.invariant, violation.entities
+                        );
+                        if ui
+                            .selectable_label(false, egui::RichText::new(label).monospace())
+                            .on_hover_text(&violation.detail)
+                            .clicked()
+                        {
+                            to_select = Some(violation.entities.clone());
+                        }
+                    }
+                });
+        });
+
+    if let Some(entities) = to_select {
+        select_only(
+            &entities,
+            &mut selection_query,
+            &mut select_writer,
+            &mut deselect_writer,
+        );
+    }
+}
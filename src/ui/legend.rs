@@ -0,0 +1,52 @@
+use bevy::prelude::*;
+use bevy_picking_egui::bevy_egui::{
+    egui::{self, Color32, Sense},
+    EguiContexts,
+};
+
+use crate::util::*;
+
+const COLOR_MODES: [ColorMode; 7] = [
+    ColorMode::Speed,
+    ColorMode::Acceleration,
+    ColorMode::Temperament,
+    ColorMode::Patience,
+    ColorMode::Lawfulness,
+    ColorMode::DriverState,
+    ColorMode::VehicleClass,
+];
+
+pub fn vehicle_color_legend_ui(
+    mut egui_contexts: EguiContexts,
+    color_mode: Res<State<ColorMode>>,
+    mut next_color_mode: ResMut<NextState<ColorMode>>,
+) {
+    egui::Window::new("Vehicle Colors")
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(10., -10.))
+        .resizable(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            let mut selected = *color_mode.get();
+
+            egui::ComboBox::from_label("Color by")
+                .selected_text(format!("{:?}", selected))
+                .show_ui(ui, |ui| {
+                    for mode in COLOR_MODES {
+                        ui.selectable_value(&mut selected, mode, format!("{:?}", mode));
+                    }
+                });
+
+            if selected != *color_mode.get() {
+                next_color_mode.set(selected);
+            }
+
+            for (label, color) in color_mode_buckets(color_mode.get()) {
+                ui.horizontal(|ui| {
+                    let [r, g, b, _] = color.as_rgba_u8();
+                    let (rect, _) = ui.allocate_exact_size(egui::vec2(12., 12.), Sense::hover());
+                    ui.painter()
+                        .rect_filled(rect, 2., Color32::from_rgb(r, g, b));
+                    ui.label(label);
+                });
+            }
+        });
+}
@@ -0,0 +1,134 @@
+use bevy::prelude::*;
+use bevy_picking_egui::bevy_egui::{
+    egui::{self, Color32, Pos2, Sense, Stroke},
+    EguiContexts,
+};
+
+use crate::components::*;
+use crate::constants::*;
+use crate::util::*;
+
+pub fn minimap_ui(
+    mut egui_contexts: EguiContexts,
+    car_query: Query<(&Transform, &Velocity, &LaneEntity), With<Car>>,
+    mut camera_query: Query<
+        (&mut Transform, &OrthographicProjection),
+        (With<MainCamera>, Without<Car>),
+    >,
+    mut next_camera_mode: ResMut<NextState<CameraMode>>,
+) {
+    let road_min = Vec2::new(LEFT_WALL, BOTTOM_WALL);
+    let road_size = Vec2::new(RIGHT_WALL, TOP_WALL) - road_min;
+    let minimap_size = egui::vec2(MINIMAP_WIDTH, MINIMAP_WIDTH * road_size.y / road_size.x);
+
+    egui::Window::new("Minimap")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-10., 10.))
+        .resizable(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            let (response, painter) = ui.allocate_painter(minimap_size, Sense::click_and_drag());
+            let rect = response.rect;
+
+            // world y points up, egui y points down
+            let to_minimap = |world: Vec2| {
+                let pct = (world - road_min) / road_size;
+                Pos2::new(
+                    rect.left() + pct.x * rect.width(),
+                    rect.bottom() - pct.y * rect.height(),
+                )
+            };
+            let to_world = |pos: Pos2| {
+                road_min
+                    + Vec2::new(
+                        (pos.x - rect.left()) / rect.width(),
+                        (rect.bottom() - pos.y) / rect.height(),
+                    ) * road_size
+            };
+
+            painter.rect_filled(rect, 0., Color32::from_gray(60));
+
+            for i in 0..=NUM_LANES {
+                let lane_x = lane_idx_to_screen_pos(i).x;
+                painter.line_segment(
+                    [
+                        to_minimap(Vec2::new(lane_x, BOTTOM_WALL)),
+                        to_minimap(Vec2::new(lane_x, TOP_WALL)),
+                    ],
+                    Stroke::new(1., Color32::from_gray(200)),
+                );
+            }
+
+            // jams: lane segments packed more densely than `JAM_DENSITY`
+            let num_segments = (road_size.y / JAM_SEGMENT_LENGTH).ceil() as usize;
+            let mut counts = vec![vec![0; num_segments]; NUM_LANES as usize];
+
+            for (transform, _, lane) in &car_query {
+                let segment =
+                    ((transform.translation.y - BOTTOM_WALL) / JAM_SEGMENT_LENGTH) as usize;
+                if let Some(count) = counts
+                    .get_mut(lane.0 as usize)
+                    .and_then(|lane_counts| lane_counts.get_mut(segment))
+                {
+                    *count += 1;
+                }
+            }
+
+            for (lane_idx, lane_counts) in counts.iter().enumerate() {
+                let lane_x = lane_idx_to_screen_pos(lane_idx as i32).x;
+
+                for (segment, count) in lane_counts.iter().enumerate() {
+                    if (*count as f32 / JAM_SEGMENT_LENGTH) <= JAM_DENSITY {
+                        continue;
+                    }
+
+                    let segment_y = BOTTOM_WALL + segment as f32 * JAM_SEGMENT_LENGTH;
+                    painter.rect_filled(
+                        egui::Rect::from_two_pos(
+                            to_minimap(Vec2::new(lane_x, segment_y)),
+                            to_minimap(Vec2::new(
+                                lane_x + LANE_WIDTH,
+                                segment_y + JAM_SEGMENT_LENGTH,
+                            )),
+                        ),
+                        0.,
+                        Color32::from_rgba_unmultiplied(255, 0, 0, 90),
+                    );
+                }
+            }
+
+            for (transform, velocity, _) in &car_query {
+                painter.circle_filled(
+                    to_minimap(transform.translation.truncate()),
+                    MINIMAP_DOT_RADIUS,
+                    speed_color(velocity.y),
+                );
+            }
+
+            let (mut camera_transform, projection) = camera_query.single_mut();
+
+            let camera_position = camera_transform.translation.truncate();
+            painter.rect_stroke(
+                egui::Rect::from_two_pos(
+                    to_minimap(camera_position + projection.area.min),
+                    to_minimap(camera_position + projection.area.max),
+                ),
+                0.,
+                Stroke::new(1., Color32::WHITE),
+            );
+
+            if let Some(pointer) = response.interact_pointer_pos() {
+                let target = to_world(pointer);
+                camera_transform.translation.x = target.x;
+                camera_transform.translation.y = target.y;
+                next_camera_mode.set(CameraMode::Free);
+            }
+        });
+}
+
+pub fn speed_color(speed: f32) -> Color32 {
+    // red when stopped, through yellow, to green at the speed limit and above
+    let pct = (speed / SPEED_LIMIT).clamp(0., 1.);
+    let red = (255. * f32::min(1., 2. * (1. - pct))) as u8;
+    let green = (255. * f32::min(1., 2. * pct)) as u8;
+
+    Color32::from_rgb(red, green, 0)
+}
@@ -0,0 +1,33 @@
+pub mod breakpoints_panel;
+pub mod driver_editor;
+pub mod heatmap_panel;
+pub mod history_panel;
+pub mod inspector;
+pub mod invariants_panel;
+pub mod legend;
+pub mod minimap;
+pub mod overlays_panel;
+pub mod plots;
+pub mod profiler_panel;
+pub mod replay_panel;
+pub mod rewind_panel;
+pub mod save_panel;
+pub mod speed_panel;
+pub mod trajectories;
+
+pub use breakpoints_panel::*;
+pub use driver_editor::*;
+pub use heatmap_panel::*;
+pub use history_panel::*;
+pub use inspector::*;
+pub use invariants_panel::*;
+pub use legend::*;
+pub use minimap::*;
+pub use overlays_panel::*;
+pub use plots::*;
+pub use profiler_panel::*;
+pub use replay_panel::*;
+pub use rewind_panel::*;
+pub use save_panel::*;
+pub use speed_panel::*;
+pub use trajectories::*;
@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+use bevy_picking_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::constants::*;
+use crate::resources::*;
+use crate::util::*;
+
+const OVERLAY_SCOPES: [OverlayScope; 3] =
+    [OverlayScope::Off, OverlayScope::Selected, OverlayScope::All];
+
+pub fn vehicle_overlays_ui(
+    mut egui_contexts: EguiContexts,
+    debug_state: Res<State<DebugState>>,
+    mut overlays: ResMut<VehicleOverlays>,
+) {
+    if debug_state.get() != &DebugState::Enabled {
+        return;
+    }
+
+    egui::Window::new("Vehicle Overlays")
+        .resizable(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            egui::Grid::new("vehicle_overlays").show(ui, |ui| {
+                scope_row(ui, "Trails", &mut overlays.trails);
+                scope_row(ui, "Lane change path", &mut overlays.predicted_path);
+                scope_row(ui, "Stopping distance", &mut overlays.stopping_envelope);
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Trail length (s)");
+                ui.add(egui::Slider::new(
+                    &mut overlays.trail_length,
+                    TRAIL_SAMPLE_PERIOD..=TRAIL_MAX_LENGTH,
+                ));
+            });
+        });
+}
+
+fn scope_row(ui: &mut egui::Ui, label: &str, scope: &mut OverlayScope) {
+    ui.label(label);
+    for option in OVERLAY_SCOPES {
+        ui.selectable_value(scope, option, format!("{:?}", option));
+    }
+    ui.end_row();
+}
@@ -0,0 +1,261 @@
+use bevy::prelude::*;
+use bevy_picking_egui::bevy_egui::{
+    egui::{self, Align2, Color32, FontId, Pos2, Sense, Shape, Stroke},
+    EguiContexts,
+};
+
+use crate::components::*;
+use crate::constants::*;
+use crate::resources::*;
+use crate::util::*;
+
+const TEMPERAMENTS: [DriverTemperament; 4] = [
+    DriverTemperament::Psychotic,
+    DriverTemperament::Aggressive,
+    DriverTemperament::Calm,
+    DriverTemperament::Passive,
+];
+
+const LANE_COLORS: [Color32; 4] = [
+    Color32::LIGHT_BLUE,
+    Color32::LIGHT_GREEN,
+    Color32::GOLD,
+    Color32::LIGHT_RED,
+];
+
+// one line on a plot: its label, color and (time, value) points
+type Series<'a> = (&'a str, Color32, Vec<Pos2>);
+
+pub fn plots_ui(
+    mut egui_contexts: EguiContexts,
+    mut config: ResMut<PlotsConfig>,
+    metrics: Res<TrafficMetrics>,
+    clock: Res<SimulationClock>,
+    query: Query<(&Velocity, &DriverAgent), With<Car>>,
+) {
+    if !config.visible {
+        return;
+    }
+
+    // samples are only taken while the simulation runs, so the plots hold still while paused
+    let contents = |ui: &mut egui::Ui, config: &mut PlotsConfig| {
+        ui.horizontal(|ui| {
+            ui.label("Window (s)");
+            ui.add(egui::Slider::new(
+                &mut config.window_length,
+                10.0..=METRICS_HISTORY,
+            ));
+            ui.checkbox(&mut config.docked, "Docked");
+        });
+
+        let window_start = clock.elapsed - config.window_length;
+        let samples: Vec<&MetricsSample> = metrics
+            .samples
+            .iter()
+            .filter(|sample| sample.time >= window_start)
+            .collect();
+
+        let series = |value: fn(&MetricsSample) -> f32| {
+            samples
+                .iter()
+                .map(|sample| Pos2::new(sample.time, value(sample)))
+                .collect::<Vec<_>>()
+        };
+
+        let time_range = (window_start, clock.elapsed);
+
+        ui.columns(2, |columns| {
+            line_plot(
+                &mut columns[0],
+                "Mean speed",
+                time_range,
+                &[("", Color32::LIGHT_BLUE, series(|sample| sample.mean_speed))],
+            );
+            line_plot(
+                &mut columns[0],
+                "Flow (cars/min/lane)",
+                time_range,
+                &[("", Color32::LIGHT_GREEN, series(|sample| sample.flow))],
+            );
+            line_plot(
+                &mut columns[0],
+                "Density (cars/1000 units/lane)",
+                time_range,
+                &[("", Color32::GOLD, series(|sample| sample.density))],
+            );
+
+            let lane_labels: Vec<String> =
+                (0..NUM_LANES).map(|lane| format!("lane {lane}")).collect();
+            let lanes: Vec<Series> = lane_labels
+                .iter()
+                .enumerate()
+                .map(|(lane, label)| {
+                    let points = samples
+                        .iter()
+                        .map(|sample| {
+                            let count = sample.cars_per_lane.get(lane).copied().unwrap_or(0);
+                            Pos2::new(sample.time, count as f32)
+                        })
+                        .collect();
+                    (
+                        label.as_str(),
+                        LANE_COLORS[lane % LANE_COLORS.len()],
+                        points,
+                    )
+                })
+                .collect();
+
+            line_plot(&mut columns[1], "Cars per lane", time_range, &lanes);
+            line_plot(
+                &mut columns[1],
+                "Lane changes per minute",
+                time_range,
+                &[(
+                    "",
+                    Color32::from_rgb(255, 0, 255),
+                    series(|sample| sample.lane_changes_per_minute),
+                )],
+            );
+            line_plot(
+                &mut columns[1],
+                "Crashes",
+                time_range,
+                &[("", Color32::RED, series(|sample| sample.crashes as f32))],
+            );
+        });
+
+        ui.separator();
+        speed_histogram(ui, &query);
+    };
+
+    let ctx = egui_contexts.ctx_mut();
+    if config.docked {
+        egui::TopBottomPanel::bottom("plots")
+            .resizable(true)
+            .show(ctx, |ui| contents(ui, &mut config));
+    } else {
+        egui::Window::new("Plots")
+            .default_width(600.)
+            .show(ctx, |ui| contents(ui, &mut config));
+    }
+}
+
+fn line_plot(ui: &mut egui::Ui, title: &str, time_range: (f32, f32), lines: &[Series]) {
+    let latest: Vec<String> = lines
+        .iter()
+        .filter_map(|(label, _, points)| {
+            let value = points.last()?.y;
+            Some(if label.is_empty() {
+                format!("{value:.1}")
+            } else {
+                format!("{label}: {value:.1}")
+            })
+        })
+        .collect();
+    ui.label(format!("{title}  {}", latest.join("  ")));
+
+    let (response, painter) = ui.allocate_painter(
+        egui::vec2(ui.available_width(), PLOT_HEIGHT),
+        Sense::hover(),
+    );
+    let rect = response.rect;
+    painter.rect_filled(rect, 2., Color32::from_gray(30));
+
+    // the y axis always starts at zero and grows to fit the largest value in view
+    let max_value = lines
+        .iter()
+        .flat_map(|(_, _, points)| points.iter().map(|point| point.y))
+        .fold(1., f32::max)
+        * 1.1;
+    let (start, end) = time_range;
+    let duration = f32::max(end - start, f32::EPSILON);
+
+    let to_screen = |point: &Pos2| {
+        Pos2::new(
+            rect.left() + (point.x - start) / duration * rect.width(),
+            rect.bottom() - point.y / max_value * rect.height(),
+        )
+    };
+
+    for (_, color, points) in lines {
+        let points: Vec<Pos2> = points.iter().map(to_screen).collect();
+        painter.add(Shape::line(points, Stroke::new(1.5, *color)));
+    }
+
+    painter.text(
+        rect.left_top() + egui::vec2(2., 2.),
+        Align2::LEFT_TOP,
+        format!("{:.0}", max_value),
+        FontId::monospace(9.),
+        Color32::GRAY,
+    );
+}
+
+fn speed_histogram(ui: &mut egui::Ui, query: &Query<(&Velocity, &DriverAgent), With<Car>>) {
+    // bars are stacked by temperament, colored as in the temperament legend
+    ui.label("Speed by temperament");
+
+    let colors = color_mode_buckets(&ColorMode::Temperament);
+    let bin_width = HISTOGRAM_MAX_SPEED / HISTOGRAM_BINS as f32;
+
+    let mut counts = vec![[0usize; TEMPERAMENTS.len()]; HISTOGRAM_BINS];
+    for (velocity, agent) in query {
+        let bin = ((velocity.y.max(0.) / bin_width) as usize).min(HISTOGRAM_BINS - 1);
+        counts[bin][agent.temperament.clone() as usize] += 1;
+    }
+
+    let max_count = counts
+        .iter()
+        .map(|bin| bin.iter().sum::<usize>())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let (response, painter) = ui.allocate_painter(
+        egui::vec2(ui.available_width(), PLOT_HEIGHT * 1.5),
+        Sense::hover(),
+    );
+    let rect = response.rect;
+    painter.rect_filled(rect, 2., Color32::from_gray(30));
+
+    let bar_width = rect.width() / HISTOGRAM_BINS as f32;
+
+    for (bin, bin_counts) in counts.iter().enumerate() {
+        let left = rect.left() + bin as f32 * bar_width;
+        let mut bottom = rect.bottom();
+
+        for (temperament, count) in bin_counts.iter().enumerate() {
+            let height = *count as f32 / max_count as f32 * rect.height();
+            let [r, g, b, _] = colors[temperament].1.as_rgba_u8();
+
+            painter.rect_filled(
+                egui::Rect::from_min_max(
+                    Pos2::new(left + 1., bottom - height),
+                    Pos2::new(left + bar_width - 1., bottom),
+                ),
+                0.,
+                Color32::from_rgb(r, g, b),
+            );
+            bottom -= height;
+        }
+    }
+
+    painter.text(
+        rect.right_bottom() - egui::vec2(2., 2.),
+        Align2::RIGHT_BOTTOM,
+        format!("{:.0}", HISTOGRAM_MAX_SPEED),
+        FontId::monospace(9.),
+        Color32::GRAY,
+    );
+
+    ui.horizontal(|ui| {
+        for (temperament, (label, color)) in TEMPERAMENTS.iter().zip(colors) {
+            let [r, g, b, _] = color.as_rgba_u8();
+            let count: usize = counts
+                .iter()
+                .map(|bin| bin[temperament.clone() as usize])
+                .sum();
+            ui.colored_label(Color32::from_rgb(r, g, b), format!("{label} ({count})"));
+        }
+    });
+}
@@ -0,0 +1,85 @@
+use bevy::{ecs::entity::Entities, prelude::*};
+use bevy_picking_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::components::*;
+use crate::constants::*;
+use crate::profiling::*;
+
+pub fn profiler_ui(
+    mut egui_contexts: EguiContexts,
+    profiler: Res<Profiler>,
+    fixed_time: Res<Time<Fixed>>,
+    car_query: Query<(), With<Car>>,
+    entities: &Entities,
+) {
+    if !profiler.visible {
+        return;
+    }
+
+    let counts = EntityCounts {
+        cars: car_query.iter().count(),
+        entities: entities.len() as usize,
+    };
+    let timestep = fixed_time.timestep();
+    let stats = profiler.stats();
+
+    egui::Window::new("Profiler")
+        .default_width(420.)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.label(format!(
+                "{} cars, {} entities",
+                counts.cars, counts.entities
+            ));
+
+            for (schedule, total) in schedule_totals(&stats) {
+                if schedule == format!("{:?}", FixedUpdate) {
+                    // what one tick costs against the real time it stands for
+                    ui.label(format!(
+                        "{schedule}: {:.3} ms per tick ({:.0}% of the {:.3} ms budget)",
+                        millis(total),
+                        total.as_secs_f64() / timestep.as_secs_f64() * 100.,
+                        millis(timestep)
+                    ));
+                } else {
+                    ui.label(format!("{schedule}: {:.3} ms per frame", millis(total)));
+                }
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("Reset").clicked() {
+                    profiler.clear();
+                }
+                if ui.button("Export report").clicked() {
+                    let report = profiler.report(&counts, timestep);
+                    match std::fs::write(PROFILE_REPORT_PATH, report) {
+                        Ok(()) => info!("saved profile report to {PROFILE_REPORT_PATH}"),
+                        Err(error) => {
+                            error!("couldn't save profile report to {PROFILE_REPORT_PATH}: {error}")
+                        }
+                    }
+                }
+            });
+
+            ui.separator();
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::Grid::new("profiler_systems")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("System");
+                        ui.strong("avg ms");
+                        ui.strong("p95 ms");
+                        ui.strong("max ms");
+                        ui.end_row();
+
+                        for system in &stats {
+                            ui.label(&system.name).on_hover_text(&system.schedule);
+                            ui.label(format!("{:.3}", millis(system.average)));
+                            ui.label(format!("{:.3}", millis(system.p95)));
+                            ui.label(format!("{:.3}", millis(system.max)));
+                            ui.end_row();
+                        }
+                    });
+            });
+        });
+}
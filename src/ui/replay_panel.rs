@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+use bevy_picking_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::constants::*;
+use crate::events::*;
+use crate::replay::*;
+use crate::util::*;
+
+pub fn replay_ui(
+    mut egui_contexts: EguiContexts,
+    debug_state: Res<State<DebugState>>,
+    simulation_mode: Res<State<SimulationMode>>,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    recorder: Res<Recorder>,
+    replay: Option<ResMut<Replay>>,
+    mut replay_writer: EventWriter<ReplayRequestEvent>,
+) {
+    let replaying = simulation_mode.get() == &SimulationMode::Replay;
+    if !replaying && debug_state.get() != &DebugState::Enabled {
+        return;
+    }
+
+    egui::Window::new("Replay")
+        .resizable(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            let Some(mut replay) = replay.filter(|_| replaying) else {
+                ui.horizontal(|ui| match &recorder.recording {
+                    Some(recording) => {
+                        if ui.button("Stop recording").clicked() {
+                            replay_writer.send(ReplayRequestEvent(ReplayRequest::StopRecording));
+                        }
+                        ui.label(format!(
+                            "{} frames, {:.1}s",
+                            recording.frames.len(),
+                            recording.duration()
+                        ));
+                    }
+                    None => {
+                        if ui.button("Record").clicked() {
+                            replay_writer.send(ReplayRequestEvent(ReplayRequest::StartRecording));
+                        }
+                    }
+                });
+
+                if ui.button(format!("Open {RECORDING_PATH}")).clicked() {
+                    replay_writer.send(ReplayRequestEvent(ReplayRequest::Open));
+                }
+                return;
+            };
+
+            ui.horizontal(|ui| {
+                let playing = pause_state.get() == &PauseState::Running;
+                if ui.button(if playing { "Pause" } else { "Play" }).clicked() {
+                    next_pause_state.set(if playing {
+                        PauseState::Paused
+                    } else {
+                        // playing from the end starts over
+                        if replay.time >= replay.recording.duration() {
+                            replay.time = 0.;
+                        }
+                        PauseState::Running
+                    });
+                }
+
+                if ui.button("Close replay").clicked() {
+                    replay_writer.send(ReplayRequestEvent(ReplayRequest::Close));
+                }
+            });
+
+            let duration = replay.recording.duration();
+            ui.horizontal(|ui| {
+                ui.label("Time");
+                ui.add(egui::Slider::new(&mut replay.time, 0.0..=duration).suffix("s"));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Speed");
+                ui.add(
+                    egui::Slider::new(&mut replay.speed, REPLAY_MIN_SPEED..=REPLAY_MAX_SPEED)
+                        .logarithmic(true)
+                        .suffix("×"),
+                );
+            });
+        });
+}
@@ -0,0 +1,72 @@
+use bevy::prelude::*;
+use bevy_picking_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::events::*;
+use crate::rewind::*;
+use crate::util::*;
+
+pub fn rewind_ui(
+    mut egui_contexts: EguiContexts,
+    pause_state: Res<State<PauseState>>,
+    simulation_mode: Res<State<SimulationMode>>,
+    mut rewind: ResMut<Rewind>,
+    mut rewind_writer: EventWriter<RewindRequestEvent>,
+) {
+    if pause_state.get() != &PauseState::Paused || simulation_mode.get() != &SimulationMode::Live {
+        return;
+    }
+
+    let latest = rewind.snapshots.len().saturating_sub(1);
+    let mut position = rewind.position();
+    let mut enabled = rewind.enabled;
+
+    egui::Window::new("Rewind")
+        .resizable(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.checkbox(&mut enabled, "Keep snapshots");
+            if rewind.snapshots.is_empty() {
+                return;
+            }
+
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(position > 0, egui::Button::new("⏴ Step back"))
+                    .clicked()
+                {
+                    rewind_writer.send(RewindRequestEvent(RewindRequest::StepBack));
+                }
+                if ui
+                    .add_enabled(position < latest, egui::Button::new("Step forward ⏵"))
+                    .clicked()
+                {
+                    rewind_writer.send(RewindRequestEvent(RewindRequest::StepForward));
+                }
+            });
+
+            let slider = egui::Slider::new(&mut position, 0..=latest)
+                .custom_formatter(|value, _| {
+                    let snapshot = &rewind.snapshots[value as usize];
+                    format!("{:.2}s", snapshot.clock.elapsed)
+                })
+                .show_value(true);
+            if ui.add(slider).changed() {
+                rewind_writer.send(RewindRequestEvent(RewindRequest::JumpTo(position)));
+            }
+
+            if position < latest {
+                ui.weak(format!(
+                    "{} ticks back; resuming discards them",
+                    latest - position
+                ));
+            }
+        });
+
+    // turning it off lets go of the snapshots already taken; a rewound world still resumes from
+    // where it is
+    if enabled != rewind.enabled {
+        rewind.enabled = enabled;
+        if !enabled {
+            rewind.snapshots.clear();
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use bevy::prelude::*;
+use bevy_picking_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::events::*;
+use crate::resources::*;
+use crate::util::*;
+
+pub fn save_ui(
+    mut egui_contexts: EguiContexts,
+    debug_state: Res<State<DebugState>>,
+    simulation_mode: Res<State<SimulationMode>>,
+    mut save_slot: ResMut<SaveSlot>,
+    mut save_writer: EventWriter<SaveRequestEvent>,
+) {
+    if debug_state.get() != &DebugState::Enabled || simulation_mode.get() != &SimulationMode::Live {
+        return;
+    }
+
+    egui::Window::new("Simulation State")
+        .resizable(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("File");
+                ui.text_edit_singleline(&mut save_slot.path);
+            });
+
+            ui.horizontal(|ui| {
+                if ui.button("Save (F5)").clicked() {
+                    save_writer.send(SaveRequestEvent(SaveRequest::Save));
+                }
+                if ui.button("Load (F9)").clicked() {
+                    save_writer.send(SaveRequestEvent(SaveRequest::Load));
+                }
+            });
+        });
+}
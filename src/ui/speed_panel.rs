@@ -0,0 +1,46 @@
+use bevy::prelude::*;
+use bevy_picking_egui::bevy_egui::{egui, EguiContexts};
+
+use crate::constants::*;
+use crate::resources::*;
+use crate::util::*;
+
+pub fn simulation_speed_ui(
+    mut egui_contexts: EguiContexts,
+    mut speed: ResMut<SimulationSpeed>,
+    clock: Res<SimulationClock>,
+    real_time: Res<Time<Real>>,
+    pause_state: Res<State<PauseState>>,
+) {
+    egui::Window::new("Simulation Speed")
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0., 10.))
+        .resizable(false)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            let mut step = speed.step;
+            let slider = egui::Slider::new(&mut step, 0..=SIMULATION_SPEEDS.len())
+                .custom_formatter(|value, _| match SIMULATION_SPEEDS.get(value as usize) {
+                    Some(multiplier) => format!("{multiplier}x"),
+                    None => "max".to_string(),
+                })
+                .show_value(true);
+            if ui.add(slider).on_hover_text("[ / ] to change").changed() {
+                speed.step = step;
+            }
+
+            ui.label(format!(
+                "sim {:.1}s / wall {:.1}s",
+                clock.elapsed,
+                real_time.elapsed_seconds()
+            ));
+
+            if pause_state.get() == &PauseState::Paused {
+                ui.weak("paused");
+            } else {
+                let mut running = format!("running at {:.2}x", speed.measured);
+                if speed.multiplier().is_none() {
+                    running.push_str(&format!(" ({} ticks per frame)", speed.ticks_per_frame));
+                }
+                ui.weak(running);
+            }
+        });
+}
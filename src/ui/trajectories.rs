@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use bevy_mod_picking::prelude::*;
+use bevy_picking_egui::bevy_egui::{
+    egui::{self, Align2, Color32, FontId, Pos2, Sense, Stroke},
+    EguiContexts,
+};
+
+use crate::components::*;
+use crate::constants::*;
+use crate::events::*;
+use crate::resources::*;
+use crate::systems::select_only;
+use crate::ui::speed_color;
+
+pub fn trajectories_ui(
+    mut egui_contexts: EguiContexts,
+    mut config: ResMut<TrajectoryViewConfig>,
+    trajectories: Res<Trajectories>,
+    clock: Res<SimulationClock>,
+    mut selection_query: Query<(Entity, &mut PickSelection, Has<SelectedEntity>), With<Car>>,
+    mut select_writer: EventWriter<SelectEntityEvent>,
+    mut deselect_writer: EventWriter<DeselectEntityEvent>,
+) {
+    if !config.visible {
+        return;
+    }
+
+    let mut clicked = None;
+
+    egui::Window::new("Space-Time Diagram")
+        .default_width(500.)
+        .show(egui_contexts.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                for lane in 0..NUM_LANES {
+                    ui.selectable_value(&mut config.lane, lane, format!("Lane {lane}"));
+                }
+                ui.separator();
+                ui.label("Window (s)");
+                ui.add(egui::Slider::new(
+                    &mut config.window_length,
+                    5.0..=TRAJECTORY_HISTORY,
+                ));
+            });
+
+            let size = egui::vec2(ui.available_width(), 300.);
+            let (response, painter) = ui.allocate_painter(size, Sense::click());
+            let rect = response.rect;
+            painter.rect_filled(rect, 2., Color32::from_gray(30));
+
+            // time runs left to right and scrolls with the simulation; position runs bottom to top
+            // like the road itself
+            let start = clock.elapsed - config.window_length;
+            let to_screen = |time: f32, position: f32| {
+                Pos2::new(
+                    rect.left() + (time - start) / config.window_length * rect.width(),
+                    rect.bottom()
+                        - (position - BOTTOM_WALL) / (TOP_WALL - BOTTOM_WALL) * rect.height(),
+                )
+            };
+
+            let pointer = response
+                .clicked()
+                .then(|| response.interact_pointer_pos())
+                .flatten();
+            let mut closest: Option<(Entity, f32)> = None;
+
+            for (entity, points) in &trajectories.vehicles {
+                let is_selected = selection_query
+                    .get(*entity)
+                    .is_ok_and(|(_, _, is_selected)| is_selected);
+
+                for (from, to) in points.iter().zip(points.iter().skip(1)) {
+                    // only segments in this lane, and not the jump when a car wraps around
+                    if to.time < start
+                        || from.lane != config.lane
+                        || to.lane != config.lane
+                        || (to.position - from.position).abs() > (TOP_WALL - BOTTOM_WALL) / 2.
+                    {
+                        continue;
+                    }
+
+                    let from_pos = to_screen(from.time.max(start), from.position);
+                    let to_pos = to_screen(to.time, to.position);
+
+                    let stroke = if is_selected {
+                        Stroke::new(2.5, Color32::WHITE)
+                    } else {
+                        Stroke::new(1., speed_color(to.speed))
+                    };
+                    painter.line_segment([from_pos, to_pos], stroke);
+
+                    if let Some(pointer) = pointer {
+                        let distance = distance_to_segment(pointer, from_pos, to_pos);
+                        if distance <= TRAJECTORY_PICK_DISTANCE
+                            && closest
+                                .is_none_or(|(_, closest_distance)| distance < closest_distance)
+                        {
+                            closest = Some((*entity, distance));
+                        }
+                    }
+                }
+            }
+
+            painter.text(
+                rect.left_bottom() + egui::vec2(2., -2.),
+                Align2::LEFT_BOTTOM,
+                format!("{:.0}s", start.max(0.)),
+                FontId::monospace(9.),
+                Color32::GRAY,
+            );
+            painter.text(
+                rect.right_bottom() - egui::vec2(2., 2.),
+                Align2::RIGHT_BOTTOM,
+                format!("{:.0}s", clock.elapsed),
+                FontId::monospace(9.),
+                Color32::GRAY,
+            );
+
+            clicked = closest.map(|(entity, _)| entity);
+        });
+
+    // clicking a trajectory selects only that vehicle, as clicking it on the road would
+    let Some(clicked) = clicked else {
+        return;
+    };
+
+    select_only(
+        &[clicked],
+        &mut selection_query,
+        &mut select_writer,
+        &mut deselect_writer,
+    );
+}
+
+fn distance_to_segment(point: Pos2, from: Pos2, to: Pos2) -> f32 {
+    let segment = to - from;
+    let length_squared = segment.length_sq();
+    if length_squared == 0. {
+        return point.distance(from);
+    }
+
+    let t = ((point - from).dot(segment) / length_squared).clamp(0., 1.);
+    point.distance(from + segment * t)
+}
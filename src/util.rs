@@ -1,7 +1,10 @@
 use crate::constants::*;
 use bevy::prelude::*;
 use lazy_static::lazy_static;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ops::RangeInclusive;
 
 #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum DebugState {
@@ -17,10 +20,140 @@ pub enum PauseState {
     Paused,
 }
 
-#[derive(Clone)]
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CameraMode {
+    Free, // wherever the user last panned / zoomed to
+    #[default]
+    FitToRoad, // the whole road in view, refit whenever the window is resized
+    FollowSelected, // centered on the selected car
+}
+
+// whether the world is driven by the simulation or by a recording being played back; see `replay`
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SimulationMode {
+    #[default]
+    Live,
+    Replay,
+}
+
+// what a car's fill color stands for; see `color_mode_buckets`
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorMode {
+    #[default]
+    Speed,
+    Acceleration,
+    Temperament,
+    Patience,
+    Lawfulness,
+    DriverState,
+    VehicleClass,
+}
+
+// which vehicles a per-vehicle overlay is drawn for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverlayScope {
+    Off,
+    Selected,
+    All,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HeatmapMetric {
+    Speed,
+    Density,
+    Flow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecisionKind {
+    CarFollowing,
+    LaneChange,
+}
+
+// the branch a driver's decision took on one tick; see `DecisionTrace`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecisionBranch {
+    // car following, decided every tick by `agent_drive_system`
+    Crashed,
+    AtTopSpeed,           // already at top speed; friction slows the car back down
+    ClearRoad,            // nothing ahead; full gas
+    OutsideBrakeDistance, // something ahead, but too far off to react to yet
+    BrakeWithinTail,      // closer than the tail distance; brake harder the closer it is
+    AccelerateRelative,   // following, but not closing in fast; gas by the relative speed
+    BrakeRelative,        // following and closing in fast
+    OpenGap,              // easing off to let a merging car in
+    Anticipate,           // braking for a slowdown further up the lane
+    // lane changes, decided by `agent_check_lane_change_system` and `agent_turn_signal_system`
+    Signal,
+    ChangeOfMind, // no longer wants the signaled lane
+    Merge,
+    MergeBlind, // merging without having seen the car in the blind spot
+    WaitForGap,
+    GiveUp, // waited `TURN_SIGNAL_TIMEOUT` without a gap
+}
+
+impl DecisionBranch {
+    pub const ALL: [DecisionBranch; 15] = [
+        DecisionBranch::Crashed,
+        DecisionBranch::AtTopSpeed,
+        DecisionBranch::ClearRoad,
+        DecisionBranch::OutsideBrakeDistance,
+        DecisionBranch::BrakeWithinTail,
+        DecisionBranch::AccelerateRelative,
+        DecisionBranch::BrakeRelative,
+        DecisionBranch::OpenGap,
+        DecisionBranch::Anticipate,
+        DecisionBranch::Signal,
+        DecisionBranch::ChangeOfMind,
+        DecisionBranch::Merge,
+        DecisionBranch::MergeBlind,
+        DecisionBranch::WaitForGap,
+        DecisionBranch::GiveUp,
+    ];
+
+    pub fn kind(&self) -> DecisionKind {
+        match self {
+            DecisionBranch::Crashed
+            | DecisionBranch::AtTopSpeed
+            | DecisionBranch::ClearRoad
+            | DecisionBranch::OutsideBrakeDistance
+            | DecisionBranch::BrakeWithinTail
+            | DecisionBranch::AccelerateRelative
+            | DecisionBranch::BrakeRelative
+            | DecisionBranch::OpenGap
+            | DecisionBranch::Anticipate => DecisionKind::CarFollowing,
+            DecisionBranch::Signal
+            | DecisionBranch::ChangeOfMind
+            | DecisionBranch::Merge
+            | DecisionBranch::MergeBlind
+            | DecisionBranch::WaitForGap
+            | DecisionBranch::GiveUp => DecisionKind::LaneChange,
+        }
+    }
+}
+
+// something that should hold for every car after every tick; see `invariant_check_system`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Invariant {
+    Overlap,          // two cars overlap without either having registered the contact
+    LaneMismatch,     // a car's `LaneEntity` doesn't match the lane it is driving in
+    OffCenter,        // between lanes with no lane change under way
+    NonFinite,        // position or velocity is NaN or infinite
+    SpeedOutOfRange,  // backwards, implausibly fast, or sliding sideways faster than a lane change
+    LaneChangeTarget, // an `ActiveLaneChange` into a lane that doesn't exist
+}
+
+// driver states, from the most to the least urgent; an agent is always in exactly one state,
+// re-evaluated every tick by `agent_state_system` (see `next_driver_state` for entry / exit conditions)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub enum DriverState {
-    Normal,
-    ChangingLanes,
+    Crashed,         // in contact with the car ahead; stays put until the contact clears
+    ChangingLanes,   // has an `ActiveLaneChange`; exits once centered in the target lane
+    Stopped,         // standing still (below `CAR_STOPPED_SPEED`) for any other reason
+    PreparingToPass, // stuck behind a slower car and looking for a gap in the passing lane
+    Yielding, // inside its tail threshold, or opening a gap for a signaling car; braking to make room
+    Following, // car ahead is within braking distance; matching its speed
+    Cruising, // nothing in braking distance; accelerating to top speed
 }
 
 // axes of DriverAgent behavior:
@@ -28,13 +161,13 @@ pub enum DriverState {
 // temperament: acceleration rates, how close to another car they'll get
 // patience: willingness to be slowed from their maximum rate (allows a larger slowdown before attempting to pass)
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub enum DriverLawfulness {
     Chaotic,
     Orderly,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub enum DriverTemperament {
     Psychotic,
     Aggressive,
@@ -42,7 +175,7 @@ pub enum DriverTemperament {
     Passive,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub enum DriverPatience {
     Enlightened,
     Patient,
@@ -50,13 +183,77 @@ pub enum DriverPatience {
     Wild,
 }
 
+// attentiveness: how reliably a driver checks its blind spot before changing lanes
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum DriverAttentiveness {
+    Vigilant,
+    Attentive,
+    Normal,
+    Distracted,
+}
+
+// judgment: how well a driver estimates distances and closing speeds; see `PerceptionNoiseConfig`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum DriverJudgment {
+    Cautious,      // reads gaps as shorter than they are
+    Accurate,      // unbiased, with little noise
+    Sloppy,        // unbiased, but noisy
+    Overconfident, // reads gaps as longer than they are
+}
+
+// which of a car's sensors picked up another car
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum Sensor {
+    Forward,
+    Rear,
+    Side, // alongside, overlapping this car's length
+}
+
+// outcome of a driver looking over a lane it wants to move into
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LaneCheck {
+    Open,
+    Blocked,
+    MissedBlindSpot, // looks open, but only because a car in the blind spot went unnoticed
+}
+
+// how a driver reacts to a car signaling to merge in directly ahead of it
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum MergeResponse {
+    OpenGap,  // backs off to let the car in
+    Ignore,   // carries on as if nothing happened
+    CloseGap, // speeds up to keep the car out
+}
+
+// named combinations of the behavior axes above, applied all at once from the driver editor;
+// see `DriverAgent::with_profile`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum DriverProfile {
+    Commuter,     // the default driver
+    SundayDriver, // slow, keeps right, leaves plenty of room
+    Speeder,      // fast and always looking to pass
+    RoadRager,    // tailgates and won't let anyone in
+    Distracted,   // average pace, poor at checking mirrors and judging gaps
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
 pub enum LaneChangeDirection {
     Left,
     Right,
     None,
 }
 
+// every `DriverState`, in declaration order
+pub const DRIVER_STATES: [DriverState; 7] = [
+    DriverState::Crashed,
+    DriverState::ChangingLanes,
+    DriverState::Stopped,
+    DriverState::PreparingToPass,
+    DriverState::Yielding,
+    DriverState::Following,
+    DriverState::Cruising,
+];
+
 lazy_static! {
     pub static ref DRIVER_TEMPERAMENT_TOP_SPEEDS: HashMap<DriverTemperament, f32> = {
         let mut map = HashMap::new();
@@ -100,24 +297,180 @@ lazy_static! {
         map.insert(DriverTemperament::Calm, 4.5);
         map.insert(DriverTemperament::Passive, 6.0);
 
+        map
+    };
+    pub static ref DRIVER_ATTENTIVENESS_BLIND_SPOT_MISS: HashMap<DriverAttentiveness, f64> = {
+        // values are the probability that a driver skips its blind spot check when it starts
+        // signaling for a lane change
+
+        let mut map = HashMap::new();
+        map.insert(DriverAttentiveness::Vigilant, 0.0);
+        map.insert(DriverAttentiveness::Attentive, 0.05);
+        map.insert(DriverAttentiveness::Normal, 0.15);
+        map.insert(DriverAttentiveness::Distracted, 0.5);
+
+        map
+    };
+    pub static ref DRIVER_JUDGMENT_NOISE: HashMap<DriverJudgment, f32> = {
+        // values scale the noise configured in `PerceptionNoiseConfig`
+
+        let mut map = HashMap::new();
+        map.insert(DriverJudgment::Cautious, 0.5);
+        map.insert(DriverJudgment::Accurate, 0.25);
+        map.insert(DriverJudgment::Sloppy, 2.0);
+        map.insert(DriverJudgment::Overconfident, 1.0);
+
+        map
+    };
+    pub static ref DRIVER_JUDGMENT_GAP_BIAS: HashMap<DriverJudgment, f32> = {
+        // values are the ratio of perceived to true distance; e.g., 0.8 means a driver sees a
+        // 100px gap as 80px
+
+        let mut map = HashMap::new();
+        map.insert(DriverJudgment::Cautious, 0.85);
+        map.insert(DriverJudgment::Accurate, 1.0);
+        map.insert(DriverJudgment::Sloppy, 1.0);
+        map.insert(DriverJudgment::Overconfident, 1.2);
+
+        map
+    };
+    pub static ref DRIVER_TEMPERAMENT_MERGE_RESPONSE: HashMap<DriverTemperament, MergeResponse> = {
+        let mut map = HashMap::new();
+        map.insert(DriverTemperament::Psychotic, MergeResponse::CloseGap);
+        map.insert(DriverTemperament::Aggressive, MergeResponse::Ignore);
+        map.insert(DriverTemperament::Calm, MergeResponse::OpenGap);
+        map.insert(DriverTemperament::Passive, MergeResponse::OpenGap);
+
+        map
+    };
+    pub static ref DRIVER_STATE_COLORS: HashMap<DriverState, Color> = {
+        // gizmo outline drawn around each car in debug mode
+        let mut map = HashMap::new();
+        map.insert(DriverState::Crashed, Color::RED);
+        map.insert(DriverState::ChangingLanes, Color::FUCHSIA);
+        map.insert(DriverState::Stopped, Color::BLACK);
+        map.insert(DriverState::PreparingToPass, Color::ORANGE);
+        map.insert(DriverState::Yielding, Color::YELLOW);
+        map.insert(DriverState::Following, Color::CYAN);
+        map.insert(DriverState::Cruising, Color::LIME_GREEN);
+
         map
     };
 }
 
+pub fn color_mode_buckets(mode: &ColorMode) -> Vec<(String, Color)> {
+    // the legend for each coloring mode; a car's bucket is an index into this list
+    let buckets: &[(&str, Color)] = match mode {
+        ColorMode::Speed => &[
+            ("Stopped", Color::RED),
+            ("Slow", Color::ORANGE),
+            ("Below limit", Color::YELLOW),
+            ("At limit", Color::LIME_GREEN),
+            ("Speeding", Color::FUCHSIA),
+        ],
+        ColorMode::Acceleration => &[
+            ("Braking hard", Color::RED),
+            ("Braking", Color::ORANGE),
+            ("Steady", Color::SILVER),
+            ("Accelerating", Color::LIME_GREEN),
+        ],
+        ColorMode::Temperament => &[
+            ("Psychotic", Color::RED),
+            ("Aggressive", Color::ORANGE),
+            ("Calm", Color::MIDNIGHT_BLUE),
+            ("Passive", Color::TEAL),
+        ],
+        ColorMode::Patience => &[
+            ("Enlightened", Color::TEAL),
+            ("Patient", Color::MIDNIGHT_BLUE),
+            ("Normal", Color::YELLOW),
+            ("Wild", Color::RED),
+        ],
+        ColorMode::Lawfulness => &[
+            ("Chaotic", Color::ORANGE_RED),
+            ("Orderly", Color::MIDNIGHT_BLUE),
+        ],
+        ColorMode::DriverState => {
+            return DRIVER_STATES
+                .iter()
+                .map(|state| (format!("{:?}", state), driver_state_color(state)))
+                .collect();
+        }
+        ColorMode::VehicleClass => &[
+            ("Car", Color::MIDNIGHT_BLUE),
+            ("Truck", Color::MAROON),
+            ("Motorcycle", Color::GOLD),
+        ],
+    };
+
+    buckets
+        .iter()
+        .map(|(label, color)| (label.to_string(), *color))
+        .collect()
+}
+
+pub fn speed_bucket(speed: f32) -> usize {
+    let pct = speed / SPEED_LIMIT;
+
+    if speed < CAR_STOPPED_SPEED {
+        0
+    } else if pct < 0.5 {
+        1
+    } else if pct < 0.9 {
+        2
+    } else if pct <= 1.1 {
+        3
+    } else {
+        4
+    }
+}
+
+pub fn acceleration_bucket(acceleration: f32) -> usize {
+    if acceleration <= -COLOR_HARD_BRAKING {
+        0
+    } else if acceleration < -COLOR_STEADY_ACCELERATION {
+        1
+    } else if acceleration <= COLOR_STEADY_ACCELERATION {
+        2
+    } else {
+        3
+    }
+}
+
+pub fn driver_state_color(state: &DriverState) -> Color {
+    DRIVER_STATE_COLORS[state]
+}
+
 pub fn driver_temperament_top_speed_pct(temperament: &DriverTemperament) -> f32 {
-    DRIVER_TEMPERAMENT_TOP_SPEEDS[&temperament]
+    DRIVER_TEMPERAMENT_TOP_SPEEDS[temperament]
 }
 
 pub fn driver_temperament_brake_threshold(temperament: &DriverTemperament) -> f32 {
-    DRIVER_TEMPERAMENT_BRAKE_THRESHOLD[&temperament]
+    DRIVER_TEMPERAMENT_BRAKE_THRESHOLD[temperament]
 }
 
 pub fn driver_temperament_tail_threshold(temperament: &DriverTemperament) -> f32 {
-    DRIVER_TEMPERAMENT_TAIL_THRESHOLD[&temperament]
+    DRIVER_TEMPERAMENT_TAIL_THRESHOLD[temperament]
+}
+
+pub fn driver_attentiveness_blind_spot_miss_pct(attentiveness: &DriverAttentiveness) -> f64 {
+    DRIVER_ATTENTIVENESS_BLIND_SPOT_MISS[attentiveness]
+}
+
+pub fn driver_judgment_noise_pct(judgment: &DriverJudgment) -> f32 {
+    DRIVER_JUDGMENT_NOISE[judgment]
+}
+
+pub fn driver_judgment_gap_bias(judgment: &DriverJudgment) -> f32 {
+    DRIVER_JUDGMENT_GAP_BIAS[judgment]
+}
+
+pub fn driver_temperament_merge_response(temperament: &DriverTemperament) -> MergeResponse {
+    DRIVER_TEMPERAMENT_MERGE_RESPONSE[temperament].clone()
 }
 
 pub fn driver_patience_min_speed_pct(patience: &DriverPatience) -> f32 {
-    DRIVER_PATIENCE_MIN_SPEEDS[&patience]
+    DRIVER_PATIENCE_MIN_SPEEDS[patience]
 }
 
 // HashMap::from([(DriverLawfulness::Chaotic, "abc")]);
@@ -172,28 +525,31 @@ pub fn lane_idx_from_screen_pos(screen_pos: &Vec2) -> i32 {
     //     screen_pos.x, LEFT_WALL, adjusted_x, lane_idx
     // );
 
-    return lane_idx;
+    lane_idx
 }
 
-pub fn lane_idx_to_center(lane_idx: i32) -> Vec3 {
-    // given the index of a lane, return the coordinates of that lane's center in screen space
-    let lane_pos = lane_idx_to_screen_pos(lane_idx);
+pub fn gaussian(rng: &mut impl Rng) -> f32 {
+    // standard normal sample (Box-Muller)
+    let u1: f32 = rng.gen_range(f32::EPSILON..1.);
+    let u2: f32 = rng.gen();
 
-    Vec3::new(lane_pos.x + (LANE_WIDTH / 2.), lane_pos.y, 0.0)
+    (-2. * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
 }
 
-pub fn cursor_pos_to_screen_space(cursor_pos: &Vec2) -> Vec2 {
-    Vec2::new(
-        cursor_pos.x - WINDOW_WIDTH_HALF,
-        cursor_pos.y - WINDOW_HEIGHT_HALF,
-    )
+pub fn lanes_occupied(transform: &Transform) -> RangeInclusive<i32> {
+    // every lane some part of the car is over; two of them while it is changing lanes
+    let left = transform.translation.x - transform.scale.x / 2.;
+    let right = transform.translation.x + transform.scale.x / 2.;
+
+    lane_idx_from_screen_pos(&Vec2::new(left + f32::EPSILON, 0.))
+        ..=lane_idx_from_screen_pos(&Vec2::new(right - f32::EPSILON, 0.))
 }
 
-pub fn screen_space_to_world_coords(screen_space: &Vec2) -> Vec2 {
-    Vec2::new(
-        screen_space.x + WINDOW_WIDTH_HALF,
-        -screen_space.y + WINDOW_HEIGHT_HALF,
-    )
+pub fn lane_idx_to_center(lane_idx: i32) -> Vec3 {
+    // given the index of a lane, return the coordinates of that lane's center in screen space
+    let lane_pos = lane_idx_to_screen_pos(lane_idx);
+
+    Vec3::new(lane_pos.x + (LANE_WIDTH / 2.), lane_pos.y, 0.0)
 }
 
 pub fn get_car_front_middle(transform: &Transform) -> Vec2 {
@@ -204,12 +560,13 @@ pub fn get_car_front_middle(transform: &Transform) -> Vec2 {
 }
 
 pub fn get_mouse_text(screen_space: &Vec2, text_coords: &Vec2) -> String {
+    // `screen_space` is the cursor in world coordinates, `text_coords` in window coordinates
     format!(
         "World: {}\n\
          UI: {}\n\
          Lane: {}",
         screen_space,
         text_coords,
-        lane_idx_from_screen_pos(&screen_space)
+        lane_idx_from_screen_pos(screen_space)
     )
 }
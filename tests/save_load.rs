@@ -0,0 +1,87 @@
+use bevy::prelude::*;
+
+use traffic::headless::*;
+use traffic::resources::*;
+use traffic::save::*;
+use traffic::scenario::*;
+use traffic::snapshot::*;
+
+// saving partway through a run and loading it in a fresh app has to carry on exactly as the run
+// would have without the interruption: the loaded cars come back under new ids, but in the same
+// order, with the same random numbers still to come
+
+const TICKS_BEFORE_SAVE: usize = 300;
+const TICKS_AFTER_SAVE: usize = 600;
+
+#[test]
+fn a_loaded_save_carries_on_like_the_uninterrupted_run() {
+    let path = std::env::temp_dir().join(format!("traffic-save-load-{}.sav", std::process::id()));
+    let path = path.to_string_lossy().to_string();
+    let scenario = Scenario {
+        cars: 40,
+        seed: 11,
+        ..default()
+    };
+
+    let mut uninterrupted = build_app(&scenario, SaveSlot::default(), false);
+    run_ticks(&mut uninterrupted, TICKS_BEFORE_SAVE);
+    save_state(&mut uninterrupted.world, &path);
+    run_ticks(&mut uninterrupted, TICKS_AFTER_SAVE);
+
+    // the scenario's own cars are replaced by the saved ones, so a different count is no matter
+    let mut resumed = build_app(
+        &Scenario {
+            cars: 10,
+            ..scenario.clone()
+        },
+        SaveSlot {
+            path: path.clone(),
+            load_on_startup: true,
+        },
+        false,
+    );
+    // the save is loaded on the first update
+    resumed.update();
+    assert_eq!(
+        resumed.world.resource::<SimulationClock>().ticks,
+        TICKS_BEFORE_SAVE as u64
+    );
+    run_ticks(&mut resumed, TICKS_AFTER_SAVE);
+    let _ = std::fs::remove_file(&path);
+
+    let expected = SimulationSnapshot::capture(&mut uninterrupted.world);
+    let actual = SimulationSnapshot::capture(&mut resumed.world);
+
+    assert_eq!(expected.clock.ticks, actual.clock.ticks);
+    assert_eq!(expected.clock.elapsed, actual.clock.elapsed);
+    assert!(
+        expected.rng == actual.rng,
+        "the random number streams diverged"
+    );
+    assert_eq!(expected.vehicles.len(), actual.vehicles.len());
+    for (expected, actual) in sorted(expected.vehicles)
+        .iter()
+        .zip(sorted(actual.vehicles))
+    {
+        assert_eq!(expected.translation, actual.translation);
+        assert_eq!(expected.velocity.0, actual.velocity.0);
+        assert_eq!(expected.lane.0, actual.lane.0);
+        assert_eq!(
+            expected.driver_agent.driver_state,
+            actual.driver_agent.driver_state
+        );
+    }
+}
+
+// each update advances one fixed tick, except the first, which only starts the clock
+fn run_ticks(app: &mut App, ticks: usize) {
+    let start = app.world.resource::<SimulationClock>().ticks;
+    while app.world.resource::<SimulationClock>().ticks < start + ticks as u64 {
+        app.update();
+    }
+}
+
+fn sorted(mut vehicles: Vec<VehicleState>) -> Vec<VehicleState> {
+    vehicles.sort_by_key(|vehicle| vehicle.entity);
+    vehicles
+}
@@ -0,0 +1,68 @@
+use std::fs;
+use std::path::Path;
+
+use traffic::headless::*;
+use traffic::scenario::*;
+
+// every scenario in tests/scenarios is run headless to the end and held to the expectations it
+// lists, so a change to the driving logic that shifts how the road behaves shows up here. a new
+// regression check is a new file in that directory
+
+#[test]
+fn scenarios_meet_their_expectations() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/scenarios");
+    let mut paths: Vec<_> = fs::read_dir(&directory)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no scenarios in {}", directory.display());
+
+    let mut failures = vec![];
+    for path in &paths {
+        let name = path.file_name().unwrap().to_string_lossy();
+        let scenario = Scenario::read(path).unwrap_or_else(|error| panic!("{name}: {error}"));
+
+        let summary = run_scenario(&scenario);
+
+        failures.extend(
+            scenario
+                .expect
+                .failures(&summary)
+                .into_iter()
+                .map(|failure| format!("{name}: {failure}")),
+        );
+    }
+
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn scenarios_over_the_road_capacity_are_rejected() {
+    let scenario = Scenario {
+        mix: TemperamentMix::parse("calm:3+aggressive:1").unwrap(),
+        ..Default::default()
+    };
+    let capacity = scenario.capacity();
+    assert!(capacity > 0);
+
+    let full = Scenario {
+        cars: capacity,
+        ..scenario.clone()
+    };
+    assert!(full.validate().is_ok());
+
+    let overfull = Scenario {
+        cars: capacity + 1,
+        ..scenario.clone()
+    };
+    assert!(overfull.validate().is_err());
+
+    // the passive drivers keep further back than anyone else in the mix
+    let passive = Scenario {
+        mix: TemperamentMix::parse("passive").unwrap(),
+        ..scenario
+    };
+    assert!(passive.capacity() < capacity);
+}